@@ -0,0 +1,125 @@
+//! Times IsoSamples::build_mesh on a terrain field and a mostly solid one, run it with `cargo bench --bench build_mesh`.
+//!
+//! Each size also times a copy of the old mesher that welded vertices by hashing the float
+//! bits of every position, so the speedup of welding by grid corner and edge is measured in
+//! the same run. The vertex and index counts show how much greedy merging saves, on the
+//! terrain with contours everywhere, where it costs more time than it saves, and on a mostly
+//! solid field, where it cuts the indices to about a third.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::render::{mesh::{Indices, PrimitiveTopology}, render_asset::RenderAssetUsages};
+use BirdBoxes::*;
+
+// Rolling hills with caves, lots of contour cells
+fn terrain(size: usize) -> IsoField{
+    let mut field = IsoField::new((size, size));
+    for y in 0..size{
+        for x in 0..size{
+            let (fx, fy) = (x as f32, y as f32);
+            let value = 0.5
+                + 0.35 * (fx * 0.11).sin() * (fy * 0.07).cos()
+                + 0.25 * (fx * 0.031 + fy * 0.053).sin();
+            field.set(x, y, value);
+        }
+    }
+    field
+}
+
+// Solid rock with a few round caves, most cells are fully solid
+fn rock(size: usize) -> IsoField{
+    let mut field = IsoField::new((size, size));
+    let step = size as f32 / 4.0;
+    for y in 0..size{
+        for x in 0..size{
+            let point = Vec2::new(x as f32, y as f32);
+            // the distance to the closest cave center, on a 4 × 4 grid
+            let cave = ((point / step).floor() + 0.5) * step;
+            field.set(x, y, point.distance(cave) / (step * 0.25) - 0.5);
+        }
+    }
+    field
+}
+
+// The mesher before edge keys: every triangle corner is looked up by the bits of its position
+fn build_mesh_float_bits(samples: IsoSamples, settings: &IsoMeshSettings) -> Mesh{
+    let IsoMeshSettings{ iso_distance, iso_level, invert, interpolation, saddle, .. } = *settings;
+    let mut used_indices = HashMap::<(u32, u32), u32>::new();
+    let mut vertexes = Vec::<Vec3>::new();
+    let mut indices = Vec::<u32>::new();
+    for (sample, x, y) in samples{
+        let t = |a, b| sample.edge_crossing(a, b, iso_level, interpolation);
+        for tri in sample.to_tri_list(iso_level, invert, saddle){
+            if tri[0] == -1 {
+                break;
+            }
+            for tri_index in tri{
+                let vertex = match tri_index {
+                    0 => Vec2::new(0.0, 0.0),
+                    1 => Vec2::new(0.0, t(0, 1)),
+                    2 => Vec2::new(0.0, 1.0),
+                    3 => Vec2::new(t(1, 2), 1.0),
+                    4 => Vec2::new(1.0, 1.0),
+                    5 => Vec2::new(1.0, t(3, 2)),
+                    6 => Vec2::new(1.0, 0.0),
+                    _ => Vec2::new(t(0, 3), 0.0),
+                };
+                let vertex = (Vec2::new(x as f32, y as f32) + vertex) * iso_distance;
+                let indice = *used_indices.entry((vertex.x.to_bits(), vertex.y.to_bits())).or_insert_with(|| {
+                    vertexes.push(vertex.extend(0.0));
+                    vertexes.len() as u32 - 1
+                });
+                indices.push(indice);
+            }
+        }
+    }
+    let normals = vec![Vec3::Z; vertexes.len()];
+    let uvs = vec![Vec2::ZERO; vertexes.len()];
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vertexes)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices))
+}
+
+fn bench(name: &str, field: &IsoField, settings: &IsoMeshSettings, build: fn(IsoSamples, &IsoMeshSettings) -> Mesh){
+    // warm up and find how many runs fit in about a second
+    let start = Instant::now();
+    let mesh = build(field.sample_all(), settings);
+    let runs = (Duration::from_secs(1).as_secs_f64() / start.elapsed().as_secs_f64()).clamp(3.0, 1000.0) as u32;
+
+    let start = Instant::now();
+    for _ in 0..runs{
+        black_box(build(field.sample_all(), black_box(settings)));
+    }
+    let per_run = start.elapsed() / runs;
+    let indices = mesh.indices().map_or(0, |indices| indices.len());
+    println!("{name:<44} {per_run:>12.3?} ({runs} runs) {:>9} vertices {indices:>9} indices", mesh.count_vertices());
+}
+
+fn main(){
+    for size in [64, 256, 1024]{
+        for (name, field) in [("terrain", terrain(size)), ("rock", rock(size))]{
+            let linear = IsoMeshSettings{
+                interpolation: IsoInterpolation::Linear,
+                ..Default::default()
+            };
+            bench(&format!("{name} {size}x{size} Linear float bits"), &field, &linear, build_mesh_float_bits);
+            for (interpolation, greedy_merge) in [
+                (IsoInterpolation::Midpoint, false),
+                (IsoInterpolation::Linear, false),
+                (IsoInterpolation::Linear, true),
+            ]{
+                let settings = IsoMeshSettings{
+                    interpolation,
+                    greedy_merge,
+                    ..Default::default()
+                };
+                bench(&format!("{name} {size}x{size} {interpolation:?} greedy:{greedy_merge}"), &field, &settings, IsoSamples::build_mesh);
+            }
+        }
+    }
+}
@@ -6,6 +6,7 @@ use bevy::prelude::*;
 fn main() {
     App::new()
         .insert_resource(ClearColor(Color::srgb(0.5, 0.5, 0.9)))
+        .insert_resource(IsoLevel(0.5))
         .add_plugins(BirdBoxesPlugin)
         .add_plugins(DefaultPlugins)
         .add_systems(Startup, test)
@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use crate::{IsoDistance, IsoField, IsoLevel};
+
+///IsoField data loaded by the asset server. Put its handle on an entity to copy the
+///field into the entity's IsoField whenever the asset loads or is reloaded,
+///the IsoField is inserted when the entity has none yet.
+///A saved IsoLevel or IsoDistance is inserted as an override on the entity
+#[derive(Asset, TypePath, Clone)]
+pub struct IsoFieldAsset{
+    pub field: IsoField,
+    pub iso_level: Option<IsoLevel>,
+    pub iso_distance: Option<IsoDistance>,
+}
+
+impl IsoFieldAsset{
+    pub fn new(field: IsoField) -> Self{
+        Self{ field, iso_level: None, iso_distance: None }
+    }
+
+    pub fn with_iso_level(mut self, iso_level: IsoLevel) -> Self{
+        self.iso_level = Some(iso_level);
+        self
+    }
+
+    pub fn with_iso_distance(mut self, iso_distance: IsoDistance) -> Self{
+        self.iso_distance = Some(iso_distance);
+        self
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn sync_field_assets(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<IsoFieldAsset>>,
+    assets: Res<Assets<IsoFieldAsset>>,
+    mut field_q: Query<(Entity, Ref<Handle<IsoFieldAsset>>, Option<&mut IsoField>)>,
+){
+    let loaded: HashSet<AssetId<IsoFieldAsset>> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies{ id } | AssetEvent::Modified{ id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    for (entity, handle, iso_field) in field_q.iter_mut(){
+        if !handle.is_changed() && !loaded.contains(&handle.id()) {
+            continue;
+        }
+        // not loaded yet, the load event brings it back here
+        let Some(asset) = assets.get(handle.id()) else {
+            continue;
+        };
+        match iso_field {
+            Some(mut iso_field) => *iso_field = asset.field.clone(),
+            None => {
+                commands.entity(entity).insert(asset.field.clone());
+            }
+        }
+        if let Some(iso_level) = asset.iso_level {
+            commands.entity(entity).insert(iso_level);
+        }
+        if let Some(iso_distance) = asset.iso_distance {
+            commands.entity(entity).insert(iso_distance);
+        }
+    }
+}
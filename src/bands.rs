@@ -0,0 +1,222 @@
+use bevy::prelude::*;
+use bevy::{sprite::Mesh2dHandle, log::info};
+use std::marker::PhantomData;
+
+use crate::{GlobalMeshSettings, IsoField, IsoInterpolation, IsoMeshSettings, IsoMultiMesh, IsoOverrides, IsoSample, UvGenerator};
+use crate::submesh::{sync_submeshes, IsoSubMesh, IsoSubMeshes, PolygonMeshBuilder};
+
+///Meshes IsoFields that have IsoBands<M>, add one per material type
+pub struct IsoBandsPlugin<M: Asset>(PhantomData<M>);
+impl<M: Asset> Default for IsoBandsPlugin<M>{
+    fn default() -> Self{
+        Self(PhantomData)
+    }
+}
+
+impl<M: Asset> Plugin for IsoBandsPlugin<M>{
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, update_bands::<M>);
+    }
+}
+
+///Splits the IsoField into filled bands between thresholds,
+///every band is meshed into its own IsoSubMesh child entity
+#[derive(Component, Clone)]
+pub struct IsoBands<M: Asset>{
+    ///The borders between the bands, they are sorted and duplicates dropped before meshing
+    pub thresholds: Vec<f32>,
+    ///The material of each band from the lowest up, one more than there are thresholds
+    pub materials: Vec<Handle<M>>,
+}
+
+impl<M: Asset> Default for IsoBands<M>{
+    fn default() -> Self{
+        Self{
+            thresholds: Vec::new(),
+            materials: Vec::new(),
+        }
+    }
+}
+
+#[derive(Bundle, Default)]
+pub struct IsoBandsBundle<M: Asset>{
+    pub iso_field: IsoField,
+    pub bands: IsoBands<M>,
+    pub multi_mesh: IsoMultiMesh,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub inherited_visibility: InheritedVisibility,
+    pub view_visibility: ViewVisibility,
+}
+
+#[allow(clippy::type_complexity)]
+fn update_bands<M: Asset>(
+    mut commands: Commands,
+    bands_q: Query<(Entity, Ref<IsoField>, Ref<IsoBands<M>>, IsoOverrides, Option<&IsoSubMeshes>)>,
+    mut sub_mesh_q: Query<(&mut Mesh2dHandle, &mut Handle<M>), With<IsoSubMesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut settings: GlobalMeshSettings,
+){
+    let changes = settings.changes();
+    for (entity, iso_field, bands, overrides, children) in bands_q.iter(){
+        let changed = children.is_none()
+            || iso_field.is_changed()
+            || bands.is_changed()
+            || changes.needs_rebuild(entity, &overrides);
+        if !changed {
+            continue;
+        }
+        info!("Band Mesh Update");
+        let settings = settings.resolve(&overrides);
+        let band_meshes = iso_field
+            .build_band_meshes(&bands.thresholds, &settings)
+            .into_iter()
+            .enumerate()
+            .map(|(band, mesh)| (mesh, bands.materials.get(band).cloned().unwrap_or_default()))
+            .collect();
+        sync_submeshes(&mut commands, entity, children, band_meshes, &mut sub_mesh_q, &mut meshes);
+    }
+}
+
+impl IsoField{
+    ///Builds one mesh for every band between the thresholds, lowest band first.
+    ///A sample is in a band when `lower < value <= upper`. The thresholds are sorted
+    ///first and duplicates and NaNs dropped, so there is one more mesh than distinct thresholds.
+    ///Neighbouring bands share their borders, so the edges are always interpolated
+    ///linearly and the IsoLevel and IsoInvert of the settings are not used
+    pub fn build_band_meshes(&self, thresholds: &[f32], settings: &IsoMeshSettings) -> Vec<Mesh>{
+        info!("Building Band Meshes {thresholds:?} {settings:?}");
+        let mut thresholds: Vec<f32> = thresholds.iter().copied().filter(|threshold| !threshold.is_nan()).collect();
+        thresholds.sort_by(f32::total_cmp);
+        thresholds.dedup();
+        let mut bands: Vec<PolygonMeshBuilder> = (0..=thresholds.len())
+            .map(|_| PolygonMeshBuilder::default())
+            .collect();
+        let band_of = |value: f32| thresholds.iter().filter(|threshold| value > **threshold).count();
+
+        let samples = self.sample_all();
+        let uvs = UvGenerator::new(&samples, settings);
+        for (IsoSample(values), x, y) in samples{
+            let origin = Vec2::new(x as f32, y as f32);
+            // counter clockwise from the bottom left
+            let corners = [
+                (origin, values[0]),
+                (origin + Vec2::new(1.0, 0.0), values[3]),
+                (origin + Vec2::new(1.0, 1.0), values[2]),
+                (origin + Vec2::new(0.0, 1.0), values[1]),
+            ];
+            let band = band_of(corners[0].1);
+            if corners.iter().all(|(_, value)| band_of(*value) == band){
+                bands[band].add_polygon(&corners, settings.iso_distance);
+                continue;
+            }
+
+            // split around the cell center so every band is clipped from the same triangles
+            let center = (origin + Vec2::splat(0.5), values.iter().sum::<f32>() / 4.0);
+            for i in 0..4{
+                let triangle = [corners[i], corners[(i + 1) % 4], center];
+                let lowest = triangle.iter().map(|(_, value)| band_of(*value)).min().unwrap();
+                let highest = triangle.iter().map(|(_, value)| band_of(*value)).max().unwrap();
+                for (band, builder) in bands.iter_mut().enumerate().take(highest + 1).skip(lowest){
+                    let mut polygon = triangle.to_vec();
+                    if band > 0 {
+                        polygon = clip(&polygon, thresholds[band - 1], true);
+                    }
+                    if band < thresholds.len() {
+                        polygon = clip(&polygon, thresholds[band], false);
+                    }
+                    builder.add_polygon(&polygon, settings.iso_distance);
+                }
+            }
+        }
+
+        bands.into_iter().map(|builder| builder.build(&uvs, settings.vertex_colors.as_ref())).collect()
+    }
+}
+
+// Sutherland Hodgman against one threshold, keeping `value > threshold` when above
+// and `value <= threshold` otherwise
+fn clip(polygon: &[(Vec2, f32)], threshold: f32, above: bool) -> Vec<(Vec2, f32)>{
+    let inside = |value: f32| (value > threshold) == above;
+    let mut out = Vec::with_capacity(polygon.len() + 2);
+    for (i, &(point, value)) in polygon.iter().enumerate(){
+        let (next_point, next_value) = polygon[(i + 1) % polygon.len()];
+        if inside(value) {
+            out.push((point, value));
+        }
+        if inside(value) != inside(next_value) {
+            // interpolate from the same end on both sides of a shared edge so the points weld
+            let ((a, a_value), (b, b_value)) = if (point.x, point.y) < (next_point.x, next_point.y) {
+                ((point, value), (next_point, next_value))
+            } else {
+                ((next_point, next_value), (point, value))
+            };
+            let t = IsoSample::crossing(a_value, b_value, threshold, IsoInterpolation::Linear);
+            out.push((a + (b - a) * t, threshold));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use bevy::render::mesh::{Indices, VertexAttributeValues};
+    use crate::noise::hash;
+    use crate::validate_mesh;
+
+    // the triangles of a mesh, in field coordinates
+    fn triangles(mesh: &Mesh, iso_distance: f32) -> Vec<[Vec2; 3]>{
+        let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else { return Vec::new() };
+        let Some(Indices::U32(indices)) = mesh.indices() else { return Vec::new() };
+        indices.chunks_exact(3)
+            .map(|tri| [0, 1, 2].map(|i| Vec3::from(positions[tri[i] as usize]).truncate() / iso_distance))
+            .collect()
+    }
+
+    fn area([a, b, c]: [Vec2; 3]) -> f32{
+        (b - a).perp_dot(c - a) * 0.5
+    }
+
+    fn contains(triangle: [Vec2; 3], point: Vec2) -> bool{
+        (0..3).all(|i| area([triangle[i], triangle[(i + 1) % 3], point]) > 0.0)
+    }
+
+    // checks the bands tile the whole field: their areas add up to it and
+    // points all over the field are inside exactly one band
+    fn assert_tiled(field: &IsoField, thresholds: &[f32], bands: usize){
+        let settings = IsoMeshSettings{ iso_distance: 0.5, ..default() };
+        let meshes = field.build_band_meshes(thresholds, &settings);
+        assert_eq!(meshes.len(), bands, "{thresholds:?}");
+        for (band, mesh) in meshes.iter().enumerate(){
+            let report = validate_mesh(mesh);
+            assert!(report.is_valid(), "{thresholds:?} band {band}: {report:?}");
+        }
+        let meshes: Vec<Vec<[Vec2; 3]>> = meshes.iter().map(|mesh| triangles(mesh, 0.5)).collect();
+
+        let total: f32 = meshes.iter().flatten().map(|triangle| area(*triangle)).sum();
+        let expected = ((field.width() - 1) * (field.height() - 1)) as f32;
+        assert!((total - expected).abs() < 1e-3, "{thresholds:?}: area {total} of {expected}");
+
+        let max = Vec2::new(field.width() as f32 - 1.0, field.height() as f32 - 1.0);
+        for i in 0..500{
+            let point = Vec2::new(hash(i, 0, 1) as f32, hash(i, 0, 2) as f32) / u32::MAX as f32 * max;
+            let count = meshes.iter().flatten().filter(|triangle| contains(**triangle, point)).count();
+            assert_eq!(count, 1, "{thresholds:?}: {point} is in {count} triangles");
+        }
+    }
+
+    #[test]
+    fn bands_tile_the_field(){
+        let values = (0..64).map(|i| hash(i, 0, 7) as f32 / u32::MAX as f32).collect();
+        let field = IsoField::new_from((8, 8), values);
+        assert_tiled(&field, &[], 1);
+        assert_tiled(&field, &[0.5], 2);
+        assert_tiled(&field, &[0.25, 0.5, 0.75], 4);
+        // unsorted, duplicated and NaN thresholds mesh like the sorted distinct ones
+        assert_tiled(&field, &[0.75, 0.25, 0.5, 0.25, f32::NAN], 4);
+        // outside of the values, the other bands are empty
+        assert_tiled(&field, &[-1.0, 2.0], 3);
+    }
+}
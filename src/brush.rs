@@ -0,0 +1,307 @@
+use bevy::prelude::*;
+
+use crate::IsoField;
+use crate::query::to_field;
+
+///The outline of an IsoBrush, in field units (one per sample)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IsoBrushShape{
+    Circle{ radius: f32 },
+    Rect{ half_size: Vec2 },
+}
+
+///How the strength of an IsoBrush fades towards its outline
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum IsoFalloff{
+    ///Full strength everywhere inside the outline
+    #[default]
+    Hard,
+    ///Eases from full strength down to nothing over the outer `width` of the brush
+    Soft{ width: f32 },
+}
+
+///How brush or stamp values are combined with the field
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IsoBlend{
+    Add,
+    Subtract,
+    ///Replaces the field value
+    #[default]
+    Set,
+    ///Keeps the lower of the two, carves with Set like values without filling
+    Min,
+    ///Keeps the higher of the two, fills without carving
+    Max,
+    ///Blurs the field towards the average of each sample and its 8 neighbours,
+    ///the brush value is the strength from 0 to 1
+    Smooth,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IsoBrush{
+    pub shape: IsoBrushShape,
+    pub falloff: IsoFalloff,
+    pub blend: IsoBlend,
+    pub value: f32,
+}
+
+impl IsoBrush{
+    pub fn circle(radius: f32, blend: IsoBlend, value: f32) -> Self{
+        Self{ shape: IsoBrushShape::Circle{ radius }, falloff: IsoFalloff::Hard, blend, value }
+    }
+
+    pub fn rect(half_size: Vec2, blend: IsoBlend, value: f32) -> Self{
+        Self{ shape: IsoBrushShape::Rect{ half_size }, falloff: IsoFalloff::Hard, blend, value }
+    }
+
+    pub fn with_falloff(mut self, falloff: IsoFalloff) -> Self{
+        self.falloff = falloff;
+        self
+    }
+
+    // how strongly the brush centered on the origin affects a point, 0..1
+    fn weight(&self, offset: Vec2) -> f32{
+        // how far inside the outline the point is
+        let depth = match self.shape {
+            IsoBrushShape::Circle{ radius } => radius - offset.length(),
+            IsoBrushShape::Rect{ half_size } => {
+                let inside = half_size - offset.abs();
+                inside.x.min(inside.y)
+            }
+        };
+        if depth < 0.0 {
+            return 0.0;
+        }
+        match self.falloff {
+            IsoFalloff::Hard => 1.0,
+            IsoFalloff::Soft{ width } => {
+                let t = (depth / width.max(f32::EPSILON)).min(1.0);
+                t * t * (3.0 - 2.0 * t)
+            }
+        }
+    }
+
+    fn extent(&self) -> Vec2{
+        match self.shape {
+            IsoBrushShape::Circle{ radius } => Vec2::splat(radius),
+            IsoBrushShape::Rect{ half_size } => half_size,
+        }
+    }
+}
+
+impl IsoField{
+    ///Paints the brush centered on `center` in field coordinates, where sample (x, y) is at (x, y).
+    ///Samples outside of the field are skipped
+    pub fn apply_brush(&mut self, brush: &IsoBrush, center: Vec2){
+        let (width, height) = (self.width(), self.height());
+        if width == 0 || height == 0 {
+            return;
+        }
+        let min = (center - brush.extent()).ceil().max(Vec2::ZERO);
+        let max = (center + brush.extent()).floor().min(Vec2::new(width as f32 - 1.0, height as f32 - 1.0));
+        if min.x > max.x || min.y > max.y {
+            return;
+        }
+        let (min_x, min_y, max_x, max_y) = (min.x as usize, min.y as usize, max.x as usize, max.y as usize);
+
+        // work out every value first, so smoothing doesn't read samples it already changed
+        let mut changes = Vec::new();
+        for y in min_y..=max_y{
+            for x in min_x..=max_x{
+                let weight = brush.weight(Vec2::new(x as f32, y as f32) - center);
+                if weight == 0.0 {
+                    continue;
+                }
+                let target = match brush.blend {
+                    IsoBlend::Smooth => self.neighbour_average(x, y),
+                    _ => brush.value,
+                };
+                let strength = match brush.blend {
+                    IsoBlend::Smooth => weight * brush.value.clamp(0.0, 1.0),
+                    _ => weight,
+                };
+                changes.push((x, y, blend(brush.blend, self.get(x, y), target, strength)));
+            }
+        }
+        for (x, y, value) in changes{
+            self.set(x, y, value);
+        }
+    }
+
+    ///Like apply_brush, but `world_center` is a world position and the brush size is in world units.
+    ///`iso_distance` and `transform` are the ones of the IsoField entity. Rect sizes follow the
+    ///scale of each axis, circles and soft widths use the average of the two since the shapes
+    ///can't be stretched. The brush stays aligned with the field when it is rotated
+    pub fn apply_brush_world(&mut self, brush: &IsoBrush, world_center: Vec2, iso_distance: f32, transform: &GlobalTransform){
+        // world units per field unit along the field's own axes
+        let scale = transform.compute_transform().scale.truncate().abs() * iso_distance;
+        let average = (scale.x + scale.y) * 0.5;
+        let mut brush = *brush;
+        brush.shape = match brush.shape {
+            IsoBrushShape::Circle{ radius } => IsoBrushShape::Circle{ radius: radius / average },
+            IsoBrushShape::Rect{ half_size } => IsoBrushShape::Rect{ half_size: half_size / scale },
+        };
+        if let IsoFalloff::Soft{ width } = brush.falloff {
+            brush.falloff = IsoFalloff::Soft{ width: width / average };
+        }
+        self.apply_brush(&brush, to_field(world_center, iso_distance, transform));
+    }
+
+    ///Blends every sample of `stamp` into the field with its bottom left sample at `offset`,
+    ///the stamp values take the place of the brush value
+    pub fn apply_stamp(&mut self, stamp: &IsoField, offset: IVec2, blend_mode: IsoBlend){
+        let mut changes = Vec::new();
+        for y in 0..stamp.height(){
+            for x in 0..stamp.width(){
+                let (field_x, field_y) = (x as i32 + offset.x, y as i32 + offset.y);
+                if field_x < 0 || field_y < 0 || field_x as usize >= self.width() || field_y as usize >= self.height() {
+                    continue;
+                }
+                let (field_x, field_y) = (field_x as usize, field_y as usize);
+                let value = stamp.get(x, y);
+                let (target, strength) = match blend_mode {
+                    IsoBlend::Smooth => (self.neighbour_average(field_x, field_y), value.clamp(0.0, 1.0)),
+                    _ => (value, 1.0),
+                };
+                changes.push((field_x, field_y, blend(blend_mode, self.get(field_x, field_y), target, strength)));
+            }
+        }
+        for (x, y, value) in changes{
+            self.set(x, y, value);
+        }
+    }
+
+    // the average of the sample and the neighbours inside the field
+    pub(crate) fn neighbour_average(&self, x: usize, y: usize) -> f32{
+        let mut sum = 0.0;
+        let mut count = 0.0;
+        for neighbour_y in y.saturating_sub(1)..=(y + 1).min(self.height() - 1){
+            for neighbour_x in x.saturating_sub(1)..=(x + 1).min(self.width() - 1){
+                sum += self.get(neighbour_x, neighbour_y);
+                count += 1.0;
+            }
+        }
+        sum / count
+    }
+}
+
+// moves `old` towards the blended value by `strength`
+fn blend(mode: IsoBlend, old: f32, value: f32, strength: f32) -> f32{
+    let blended = match mode {
+        IsoBlend::Add => old + value,
+        IsoBlend::Subtract => old - value,
+        IsoBlend::Set | IsoBlend::Smooth => value,
+        IsoBlend::Min => old.min(value),
+        IsoBlend::Max => old.max(value),
+    };
+    old + (blended - old) * strength
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    // sample (x, y) is (x + 3 y)², so the neighbour average of the middle isn't its own value
+    fn squares() -> IsoField{
+        IsoField::new_from((3, 3), (0..9).map(|i| (i * i) as f32).collect())
+    }
+
+    // the field after painting only its middle sample
+    fn blended(blend: IsoBlend, value: f32) -> Vec<f32>{
+        let mut field = squares();
+        field.apply_brush(&IsoBrush::rect(Vec2::splat(0.5), blend, value), Vec2::ONE);
+        field.values().to_vec()
+    }
+
+    fn with_middle(value: f32) -> Vec<f32>{
+        let mut values = squares().values().to_vec();
+        values[4] = value;
+        values
+    }
+
+    #[test]
+    fn blend_modes(){
+        assert_eq!(blended(IsoBlend::Add, 2.5), with_middle(18.5));
+        assert_eq!(blended(IsoBlend::Subtract, 2.5), with_middle(13.5));
+        assert_eq!(blended(IsoBlend::Set, 2.5), with_middle(2.5));
+        assert_eq!(blended(IsoBlend::Min, 2.5), with_middle(2.5));
+        assert_eq!(blended(IsoBlend::Min, 20.0), with_middle(16.0));
+        assert_eq!(blended(IsoBlend::Max, 2.5), with_middle(16.0));
+        assert_eq!(blended(IsoBlend::Max, 20.0), with_middle(20.0));
+        // halfway from 16 to the average of all nine, 204 / 9
+        let smoothed = blended(IsoBlend::Smooth, 0.5);
+        assert!((smoothed[4] - (16.0 + 204.0 / 9.0) / 2.0).abs() < 1e-4, "{smoothed:?}");
+        assert_eq!(smoothed[..4], squares().values()[..4]);
+        assert_eq!(blended(IsoBlend::Smooth, 0.0), with_middle(16.0));
+    }
+
+    #[test]
+    fn smooth_reads_the_old_values(){
+        let mut field = squares();
+        field.apply_brush(&IsoBrush::circle(5.0, IsoBlend::Smooth, 1.0), Vec2::ONE);
+        for y in 0..3{
+            for x in 0..3{
+                assert!((field.get(x, y) - squares().neighbour_average(x, y)).abs() < 1e-4, "{:?}", field.values());
+            }
+        }
+    }
+
+    #[test]
+    fn falloff(){
+        let mut field = IsoField::new((9, 9));
+        let brush = IsoBrush::circle(4.0, IsoBlend::Set, 1.0).with_falloff(IsoFalloff::Soft{ width: 2.0 });
+        field.apply_brush(&brush, Vec2::splat(4.0));
+        // full strength up to 2 from the center, halfway at 3 and nothing at 4
+        assert_eq!(field.get(4, 4), 1.0);
+        assert_eq!(field.get(6, 4), 1.0);
+        assert_eq!(field.get(4, 7), 0.5);
+        assert_eq!(field.get(0, 4), 0.0);
+        assert_eq!(field.get(8, 8), 0.0);
+        assert!(field.get(4, 6) > field.get(5, 6) && field.get(5, 6) > field.get(6, 6));
+    }
+
+    #[test]
+    fn shapes_and_border(){
+        let painted = |field: &IsoField| field.values().iter().filter(|value| **value != 0.0).count();
+        let mut field = IsoField::new((9, 9));
+        field.apply_brush(&IsoBrush::rect(Vec2::new(2.0, 1.0), IsoBlend::Add, 1.0), Vec2::splat(4.0));
+        assert_eq!(painted(&field), 15);
+        assert_eq!(field.get(6, 5), 1.0);
+        assert_eq!(field.get(4, 6), 0.0);
+
+        // only the quarter inside the field
+        let mut field = IsoField::new((9, 9));
+        field.apply_brush(&IsoBrush::circle(2.0, IsoBlend::Add, 1.0), Vec2::ZERO);
+        assert_eq!(painted(&field), 6);
+        field.apply_brush(&IsoBrush::circle(2.0, IsoBlend::Add, 1.0), Vec2::splat(-5.0));
+        assert_eq!(painted(&field), 6);
+    }
+
+    #[test]
+    fn world_brush_follows_the_transform(){
+        let painted = |field: &IsoField| field.values().iter().filter(|value| **value != 0.0).count();
+        // one field unit is 1 × 2 world units, and the field starts at (10, 0)
+        let transform = GlobalTransform::from(Transform::from_xyz(10.0, 0.0, 0.0).with_scale(Vec3::new(2.0, 4.0, 1.0)));
+        let mut field = IsoField::new((9, 9));
+        field.apply_brush_world(&IsoBrush::rect(Vec2::new(2.0, 2.0), IsoBlend::Add, 1.0), Vec2::new(14.0, 8.0), 0.5, &transform);
+        let mut expected = IsoField::new((9, 9));
+        expected.apply_brush(&IsoBrush::rect(Vec2::new(2.0, 1.0), IsoBlend::Add, 1.0), Vec2::splat(4.0));
+        assert_eq!(field.values(), expected.values());
+        assert_eq!(painted(&field), 15);
+
+        // rotated a quarter turn with 1.5 world units per field unit
+        let transform = GlobalTransform::from(
+            Transform::from_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)).with_scale(Vec3::splat(3.0))
+        );
+        let brush = IsoBrush::circle(3.0, IsoBlend::Set, 1.0).with_falloff(IsoFalloff::Soft{ width: 1.5 });
+        let mut field = IsoField::new((9, 9));
+        field.apply_brush_world(&brush, Vec2::new(-6.0, 6.0), 0.5, &transform);
+        let mut expected = IsoField::new((9, 9));
+        expected.apply_brush(&IsoBrush::circle(2.0, IsoBlend::Set, 1.0).with_falloff(IsoFalloff::Soft{ width: 1.0 }), Vec2::splat(4.0));
+        for (value, expected_value) in field.values().iter().zip(expected.values()){
+            assert!((value - expected_value).abs() < 1e-4, "{:?} != {:?}", field.values(), expected.values());
+        }
+        // the samples 2 from the center are right on the outline
+        assert_eq!(field.values().iter().filter(|value| **value > 1e-3).count(), 9);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::IsoField;
+use crate::noise::{hash, unit};
+
+///Settings for IsoField::generate_caves, the roguelike cave technique: random fill
+///smoothed by birth and survival rules over the 8 neighbours of every sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IsoCaves{
+    pub seed: u32,
+    ///The chance of a sample starting solid
+    pub fill: f32,
+    ///An empty sample turns solid with at least this many solid neighbours
+    pub birth: u8,
+    ///A solid sample stays solid with at least this many solid neighbours
+    pub survival: u8,
+    pub iterations: u32,
+    ///Keep the outermost samples solid and count the ones past the edge as solid too,
+    ///so the caves are closed off
+    pub solid_border: bool,
+    ///Box blur passes over the solid (1) and empty (0) result,
+    ///0 keeps it blocky and more passes round the contours off
+    pub blur: u32,
+}
+
+impl Default for IsoCaves{
+    fn default() -> Self{
+        Self{
+            seed: 0,
+            fill: 0.45,
+            birth: 5,
+            survival: 4,
+            iterations: 5,
+            solid_border: true,
+            blur: 1,
+        }
+    }
+}
+
+impl IsoField{
+    ///Replaces every sample with a generated cave, solid samples are 1 and empty ones 0
+    ///before blurring, so an IsoLevel of 0.5 sits on the cave walls
+    pub fn generate_caves(&mut self, caves: &IsoCaves){
+        let (width, height) = (self.width(), self.height());
+        let border = |index: usize| {
+            let (x, y) = (index % width, index / width);
+            caves.solid_border && (x == 0 || y == 0 || x + 1 == width || y + 1 == height)
+        };
+        let mut solid: Vec<bool> = (0..width * height)
+            .map(|index| border(index) || unit(hash((index % width) as i32, (index / width) as i32, caves.seed)) < caves.fill)
+            .collect();
+
+        for _ in 0..caves.iterations{
+            let neighbours = |x: usize, y: usize| {
+                let mut count = 0;
+                for dy in -1..=1_isize{
+                    for dx in -1..=1_isize{
+                        if dx == 0 && dy == 0 {
+                            continue;
+                        }
+                        let (nx, ny) = (x as isize + dx, y as isize + dy);
+                        let inside = nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height;
+                        let is_solid = if inside { solid[ny as usize * width + nx as usize] } else { caves.solid_border };
+                        count += is_solid as u8;
+                    }
+                }
+                count
+            };
+            solid = (0..width * height)
+                .map(|index| {
+                    let count = neighbours(index % width, index / width);
+                    border(index) || if solid[index] { count >= caves.survival } else { count >= caves.birth }
+                })
+                .collect();
+        }
+
+        for (index, is_solid) in solid.iter().enumerate(){
+            self.set(index % width, index / width, if *is_solid { 1.0 } else { 0.0 });
+        }
+        for _ in 0..caves.blur{
+            let blurred: Vec<f32> = (0..width * height)
+                .map(|index| if border(index) { 1.0 } else { self.neighbour_average(index % width, index / width) })
+                .collect();
+            for (index, value) in blurred.into_iter().enumerate(){
+                self.set(index % width, index / width, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use bevy::prelude::default;
+
+    fn generate(size: (usize, usize), caves: &IsoCaves) -> IsoField{
+        let mut field = IsoField::new(size);
+        field.generate_caves(caves);
+        field
+    }
+
+    fn values(field: &IsoField) -> Vec<f32>{
+        (0..field.height()).flat_map(|y| (0..field.width()).map(move |x| field.get(x, y))).collect()
+    }
+
+    #[test]
+    fn same_seed_same_caves(){
+        for seed in [0, 1, 42]{
+            let settings = IsoCaves{ seed, ..default() };
+            assert_eq!(values(&generate((40, 30), &settings)), values(&generate((40, 30), &settings)), "seed {seed}");
+        }
+        let other = IsoCaves{ seed: 1, ..default() };
+        assert_ne!(values(&generate((40, 30), &IsoCaves::default())), values(&generate((40, 30), &other)));
+    }
+
+    #[test]
+    fn border_stays_solid(){
+        for seed in 0..20{
+            for blur in [0, 1, 3]{
+                let field = generate((40, 30), &IsoCaves{ seed, blur, ..default() });
+                for y in 0..30{
+                    for x in 0..40{
+                        if x == 0 || y == 0 || x == 39 || y == 29 {
+                            assert_eq!(field.get(x, y), 1.0, "seed {seed} blur {blur} at {x}, {y}");
+                        }
+                    }
+                }
+            }
+        }
+        // without it the random fill reaches the edge
+        let open = generate((40, 30), &IsoCaves{ solid_border: false, blur: 0, ..default() });
+        assert!((0..40).any(|x| open.get(x, 0) == 0.0));
+    }
+
+    #[test]
+    fn blur_stays_in_range(){
+        for seed in 0..10{
+            let blocky = values(&generate((32, 32), &IsoCaves{ seed, blur: 0, ..default() }));
+            assert!(blocky.iter().all(|value| *value == 0.0 || *value == 1.0), "seed {seed}");
+            for blur in [1, 2, 5]{
+                let blurred = values(&generate((32, 32), &IsoCaves{ seed, blur, ..default() }));
+                assert!(blurred.iter().all(|value| (0.0..=1.0).contains(value)), "seed {seed} blur {blur}");
+                assert!(blurred.iter().any(|value| *value > 0.0 && *value < 1.0), "seed {seed} blur {blur}");
+            }
+        }
+    }
+}
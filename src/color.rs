@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+
+///Maps field values to colors, blending linearly between the stops
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct IsoGradient(Vec<(f32, LinearRgba)>);
+
+impl IsoGradient{
+    ///Stops are (field value, color), they don't need to be sorted
+    pub fn new<C: Into<LinearRgba>>(stops: impl IntoIterator<Item = (f32, C)>) -> Self{
+        let mut stops: Vec<(f32, LinearRgba)> = stops
+            .into_iter()
+            .map(|(value, color)| (value, color.into()))
+            .collect();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self(stops)
+    }
+
+    ///The color for a field value, values past the ends get the end colors
+    pub fn sample(&self, value: f32) -> LinearRgba{
+        let Some(first) = self.0.first() else {
+            return LinearRgba::WHITE;
+        };
+        if value <= first.0 {
+            return first.1;
+        }
+        for pair in self.0.windows(2){
+            let ((from, from_color), (to, to_color)) = (pair[0], pair[1]);
+            if value <= to {
+                let t = (value - from) / (to - from).max(f32::EPSILON);
+                return from_color.mix(&to_color, t);
+            }
+        }
+        self.0[self.0.len() - 1].1
+    }
+}
+
+///When set, build_mesh writes Mesh::ATTRIBUTE_COLOR from the field value at
+///every vertex. Greedy merging is skipped so the colors keep their detail
+#[derive(Resource, Debug, Default, Clone, PartialEq, Reflect)]
+#[reflect(Resource, Default)]
+pub struct IsoVertexColors(pub Option<IsoGradient>);
+
+pub(crate) fn vertex_colors(values: &[f32], gradient: &IsoGradient) -> Vec<[f32; 4]>{
+    values.iter().map(|value| gradient.sample(*value).to_f32_array()).collect()
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use bevy::render::mesh::VertexAttributeValues;
+    use crate::{IsoField, IsoMeshSettings};
+
+    #[test]
+    fn sample_the_stops(){
+        // given out of order
+        let gradient = IsoGradient::new([(1.0, LinearRgba::BLUE), (0.0, LinearRgba::RED), (0.5, LinearRgba::GREEN)]);
+        assert_eq!(gradient.sample(0.0), LinearRgba::RED);
+        assert_eq!(gradient.sample(0.5), LinearRgba::GREEN);
+        assert_eq!(gradient.sample(1.0), LinearRgba::BLUE);
+        assert_eq!(gradient.sample(0.25), LinearRgba::rgb(0.5, 0.5, 0.0));
+        assert_eq!(gradient.sample(0.875), LinearRgba::rgb(0.0, 0.25, 0.75));
+        assert_eq!(gradient.sample(-3.0), LinearRgba::RED);
+        assert_eq!(gradient.sample(7.0), LinearRgba::BLUE);
+        assert_eq!(IsoGradient::new::<LinearRgba>([]).sample(0.5), LinearRgba::WHITE);
+    }
+
+    #[test]
+    fn a_color_per_vertex(){
+        let mut field = IsoField::new((4, 4));
+        for y in 0..4{
+            for x in 0..4{
+                field.set(x, y, (x + y) as f32 * 0.5);
+            }
+        }
+        let gradient = IsoGradient::new([(0.0, LinearRgba::BLACK), (3.0, LinearRgba::WHITE)]);
+        for greedy_merge in [false, true]{
+            let settings = IsoMeshSettings{ greedy_merge, vertex_colors: Some(gradient.clone()), ..default() };
+            let mesh = field.sample_all().build_mesh(&settings);
+            let Some(VertexAttributeValues::Float32x4(colors)) = mesh.attribute(Mesh::ATTRIBUTE_COLOR) else { panic!("no colors") };
+            assert!(mesh.count_vertices() > 0);
+            assert_eq!(colors.len(), mesh.count_vertices(), "merge {greedy_merge}");
+            // the top right corner is the most solid sample
+            assert!(colors.contains(&gradient.sample(3.0).to_f32_array()), "merge {greedy_merge}");
+        }
+        assert!(field.sample_all().build_mesh(&IsoMeshSettings::default()).attribute(Mesh::ATTRIBUTE_COLOR).is_none());
+    }
+}
@@ -0,0 +1,238 @@
+use bevy::prelude::*;
+use bevy::{
+    render::{
+        mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology
+    }, log::info,
+};
+
+use crate::{is_solid, GlobalMeshSettings, IsoField, IsoMeshSettings, IsoOverrides, IsoSample, UvGenerator};
+use crate::color::vertex_colors;
+
+///Extrudes the IsoField into a 3D mesh `depth` deep, centered on z = 0.
+///The entity gets a Handle<Mesh> instead of a Mesh2dHandle, so it can be drawn
+///with a StandardMaterial like a PbrBundle
+#[derive(Component, Debug, Clone, Copy)]
+pub struct IsoExtrusion{
+    pub depth: f32,
+}
+impl Default for IsoExtrusion{
+    fn default() -> Self{
+        Self{ depth: 1.0 }
+    }
+}
+
+#[derive(Bundle, Default)]
+pub struct IsoExtrusionBundle<M: Asset>{
+    pub iso_field: IsoField,
+    pub extrusion: IsoExtrusion,
+    pub material: Handle<M>,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub inherited_visibility: InheritedVisibility,
+    pub view_visibility: ViewVisibility,
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_extrusions(
+    mut commands: Commands,
+    mut extrusion_q: Query<(Entity, Ref<IsoField>, Ref<IsoExtrusion>, Option<&mut Handle<Mesh>>, IsoOverrides)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut settings: GlobalMeshSettings,
+){
+    let changes = settings.changes();
+    for (entity, iso_field, extrusion, handle, overrides) in extrusion_q.iter_mut(){
+        let changed = handle.is_none()
+            || iso_field.is_changed()
+            || extrusion.is_changed()
+            || changes.needs_rebuild(entity, &overrides);
+        if !changed {
+            continue;
+        }
+        info!("Extruded Mesh Update");
+        let settings = settings.resolve(&overrides);
+        let mesh = iso_field.build_extruded_mesh(extrusion.depth, &settings);
+        match handle {
+            // a default handle from a bundle has no mesh behind it yet
+            Some(mut handle) => match meshes.get_mut(handle.id()) {
+                Some(stored_mesh) => *stored_mesh = mesh,
+                None => *handle = meshes.add(mesh),
+            },
+            None => {
+                commands.entity(entity).insert(meshes.add(mesh));
+            }
+        }
+    }
+}
+
+impl IsoField{
+    ///Builds a 3D mesh from the marching squares shape: a front cap at `depth / 2`
+    ///facing +Z, a back cap at `-depth / 2` facing -Z and walls along the contour
+    ///and the solid parts of the field border, with flat outward normals.
+    ///The caps use the uvs of build_mesh, on the walls u runs along the contour
+    ///and v from the front (0) to the back (1), both in units of `depth`
+    pub fn build_extruded_mesh(&self, depth: f32, settings: &IsoMeshSettings) -> Mesh{
+        info!("Building Extruded Mesh {depth} {settings:?}");
+        let mut buffers = ExtrudedBuffers::default();
+        let samples = self.sample_all();
+        let uvs = UvGenerator::new(&samples, settings);
+        let lines = samples.isolines(settings);
+        let (x_size, y_size) = (samples.x_size, samples.y_size);
+        let cap = samples.mesh_buffers(settings);
+
+        let half = depth / 2.0;
+        for (z, normal) in [(half, Vec3::Z), (-half, Vec3::NEG_Z)]{
+            let first = buffers.positions.len() as u32;
+            for (vertex, value) in cap.vertexes.iter().zip(&cap.values){
+                buffers.push(vertex.with_z(z), normal, uvs.uv(vertex.truncate()), *value);
+            }
+            // build_mesh winds towards +Z, the back cap turns them around
+            for tri in cap.indices.chunks_exact(3){
+                if normal == Vec3::Z {
+                    buffers.indices.extend([first + tri[0], first + tri[1], first + tri[2]]);
+                } else {
+                    buffers.indices.extend([first + tri[0], first + tri[2], first + tri[1]]);
+                }
+            }
+        }
+
+        // isolines keep the inside on their left, so the outside is on the right
+        for line in lines{
+            let count = if line.closed { line.points.len() } else { line.points.len().saturating_sub(1) };
+            let mut length = 0.0;
+            for i in 0..count{
+                let start = line.points[i] * settings.iso_distance;
+                let end = line.points[(i + 1) % line.points.len()] * settings.iso_distance;
+                buffers.wall((start, settings.iso_level), (end, settings.iso_level), half, &mut length, depth);
+            }
+        }
+
+        // the border counter clockwise, so the field is on the left as well
+        if x_size > 1 && y_size > 1 {
+            let mut border = Vec::new();
+            border.extend((0..x_size - 1).map(|x| (x, 0)));
+            border.extend((0..y_size - 1).map(|y| (x_size - 1, y)));
+            border.extend((1..x_size).rev().map(|x| (x, y_size - 1)));
+            border.extend((1..y_size).rev().map(|y| (0, y)));
+            let mut length = 0.0;
+            for (i, from) in border.iter().enumerate(){
+                let to = border[(i + 1) % border.len()];
+                if let Some((start, end)) = self.solid_border(*from, to, settings){
+                    buffers.wall(
+                        (start.0 * settings.iso_distance, start.1),
+                        (end.0 * settings.iso_distance, end.1),
+                        half,
+                        &mut length,
+                        depth,
+                    );
+                }
+            }
+        }
+
+        buffers.build(settings)
+    }
+
+    // The solid part of the border edge between two neighbouring samples,
+    // with its field values
+    fn solid_border(&self, from: (usize, usize), to: (usize, usize), settings: &IsoMeshSettings) -> Option<((Vec2, f32), (Vec2, f32))>{
+        let point = |(x, y): (usize, usize)| (Vec2::new(x as f32, y as f32), self.get(x, y));
+        let (from, to) = (point(from), point(to));
+        let solid = |value: f32| is_solid(value, settings.iso_level, settings.invert);
+        match (solid(from.1), solid(to.1)) {
+            (true, true) => Some((from, to)),
+            (false, false) => None,
+            (from_solid, _) => {
+                // from the lower sample, like the cell edges, so it meets the isoline
+                let (low, high) = if (from.0.x, from.0.y) < (to.0.x, to.0.y) { (from, to) } else { (to, from) };
+                let t = IsoSample::crossing(low.1, high.1, settings.iso_level, settings.interpolation);
+                let crossing = (low.0 + (high.0 - low.0) * t, settings.iso_level);
+                Some(if from_solid { (from, crossing) } else { (crossing, to) })
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct ExtrudedBuffers{
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    // field value at each vertex
+    values: Vec<f32>,
+    indices: Vec<u32>,
+}
+
+impl ExtrudedBuffers{
+    fn push(&mut self, position: Vec3, normal: Vec3, uv: Vec2, value: f32) -> u32{
+        self.positions.push(position);
+        self.normals.push(normal);
+        self.uvs.push(uv);
+        self.values.push(value);
+        self.positions.len() as u32 - 1
+    }
+
+    // a quad from `start` to `end` facing to the right of that direction
+    fn wall(&mut self, start: (Vec2, f32), end: (Vec2, f32), half: f32, length: &mut f32, depth: f32){
+        let along = end.0 - start.0;
+        if along.length_squared() <= f32::EPSILON * f32::EPSILON {
+            return;
+        }
+        let normal = Vec3::new(along.y, -along.x, 0.0).normalize();
+        let scale = depth.max(f32::EPSILON);
+        let (u_start, u_end) = (*length / scale, (*length + along.length()) / scale);
+        *length += along.length();
+
+        let start_front = self.push(start.0.extend(half), normal, Vec2::new(u_start, 0.0), start.1);
+        let end_front = self.push(end.0.extend(half), normal, Vec2::new(u_end, 0.0), end.1);
+        let end_back = self.push(end.0.extend(-half), normal, Vec2::new(u_end, 1.0), end.1);
+        let start_back = self.push(start.0.extend(-half), normal, Vec2::new(u_start, 1.0), start.1);
+        self.indices.extend([start_front, start_back, end_back, start_front, end_back, end_front]);
+    }
+
+    fn build(self, settings: &IsoMeshSettings) -> Mesh{
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs)
+            .with_inserted_indices(Indices::U32(self.indices));
+        if let Some(gradient) = &settings.vertex_colors {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vertex_colors(&self.values, gradient));
+        }
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::noise::hash;
+    use crate::{validate_mesh, SaddleResolution};
+
+    #[test]
+    fn extruded_meshes_are_valid(){
+        for seed in 0..100{
+            // samples right on the level and solid along the border as well
+            let values = (0..36).map(|i| [0.0, 0.5, 1.0, 1.0][hash(i, 0, seed) as usize % 4]).collect();
+            let field = IsoField::new_from((6, 6), values);
+            for saddle in [SaddleResolution::Join, SaddleResolution::Split, SaddleResolution::Decide]{
+                for (invert, greedy_merge) in [(false, false), (true, false), (false, true)]{
+                    let settings = IsoMeshSettings{ saddle, invert, greedy_merge, iso_level: 0.5, iso_distance: 0.5, ..default() };
+                    let report = validate_mesh(&field.build_extruded_mesh(2.0, &settings));
+                    assert!(report.is_valid(), "seed {seed} {saddle:?} invert {invert} merge {greedy_merge}: {report:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn solid_field_is_a_box(){
+        let field = IsoField::new_from((3, 3), vec![1.0; 9]);
+        let mesh = field.build_extruded_mesh(1.0, &IsoMeshSettings{ iso_level: 0.5, ..default() });
+        assert!(validate_mesh(&mesh).is_valid());
+        // two caps of 4 cells and a wall quad along each of the 8 border edges
+        assert_eq!(mesh.indices().unwrap().len(), (2 * 4 * 2 + 8 * 2) * 3);
+    }
+}
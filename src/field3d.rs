@@ -0,0 +1,595 @@
+use bevy::prelude::*;
+use bevy::{
+    render::{
+        mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology
+    }, utils::HashMap, log::info,
+};
+
+use crate::{is_solid, GlobalMeshSettings, IsoInterpolation, IsoMeshSettings, IsoOverrides, IsoSample, IsoUvMode};
+
+#[derive(Bundle, Default)]
+pub struct IsoField3dBundle<M: Asset>{
+    pub iso_field: IsoField3d,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub inherited_visibility: InheritedVisibility,
+    pub material: Handle<M>,
+    pub view_visibility: ViewVisibility,
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn add_mesh_3d(
+    mut commands: Commands,
+    iso_field_q: Query<(&IsoField3d, Entity, IsoOverrides), Without<Handle<Mesh>>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    settings: GlobalMeshSettings,
+){
+    for (field, entity, overrides) in iso_field_q.iter(){
+        info!("New 3d Mesh");
+        let settings = settings.resolve(&overrides);
+        let mesh = meshes.add(field
+                    .sample_all()
+                    .build_mesh(&settings));
+        commands.entity(entity).insert(mesh);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_mesh_3d(
+    iso_field_q: Query<(Entity, Ref<IsoField3d>, &Handle<Mesh>, IsoOverrides)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut settings: GlobalMeshSettings,
+){
+    let changes = settings.changes();
+    for (entity, iso_field, handle, overrides) in iso_field_q.iter(){
+        if !(iso_field.is_changed() || changes.needs_rebuild(entity, &overrides)) {
+            continue;
+        }
+        info!("3d Mesh Update");
+        let settings = settings.resolve(&overrides);
+        let mesh = iso_field
+                .sample_all()
+                .build_mesh(&settings);
+        if let Some(stored_mesh) = meshes.get_mut(handle.id()){
+            *stored_mesh = mesh;
+        }
+    }
+}
+
+///A 3d grid of samples meshed with marching cubes, the counterpart of IsoField.
+///It uses the same plugin resources and overrides, except for the ones that only
+///make sense in 2d (SaddleResolution, IsoGreedyMerge and IsoVertexColors)
+#[derive(Component, Default)]
+pub struct IsoField3d{
+    x_size: usize,
+    y_size: usize,
+    field: Vec<f32>,
+}
+
+type Size3d = (usize, usize, usize);
+
+impl IsoField3d {
+    pub fn new(size: impl Into<Size3d>) -> Self{
+        let (x, y, z): Size3d = size.into();
+        Self{
+            x_size: x,
+            y_size: y,
+            field: vec![0.0; x * y * z],
+        }
+    }
+
+    ///`vec` is x first, then y, then z
+    pub fn new_from(size: impl Into<Size3d>, vec: Vec<f32>) -> Self {
+        let (x, y, _z): Size3d = size.into();
+        #[cfg(debug_assertions)]
+        if vec.len() % (x * y) != 0 {
+            panic!("vec len and size do not match");
+        }
+        Self{
+            x_size: x,
+            y_size: y,
+            field: vec,
+        }
+    }
+}
+
+impl IsoField3d {
+    pub fn get(&self, x: usize, y: usize, z: usize) -> f32{
+        self.field[self.index(x, y, z)]
+    }
+    pub fn set(&mut self, x: usize, y: usize, z: usize, val: f32){
+        let index = self.index(x, y, z);
+        self.field[index] = val
+    }
+    pub fn sample(&self, x: usize, y: usize, z: usize) -> IsoSample3d{
+        IsoSample3d(CORNERS.map(|[dx, dy, dz]| self.get(x + dx, y + dy, z + dz)))
+    }
+    pub fn sample_all(&self) -> IsoSamples3d {
+        let mut samples = Vec::new();
+        let z_size = self.field.len() / (self.x_size * self.y_size).max(1);
+        for z in 0..z_size.saturating_sub(1){
+            for y in 0..self.y_size.saturating_sub(1){
+                for x in 0..self.x_size.saturating_sub(1){
+                    samples.push((self.sample(x, y, z), x, y, z));
+                }
+            }
+        }
+        IsoSamples3d{
+            samples,
+            x_size: self.x_size,
+            y_size: self.y_size,
+        }
+    }
+    fn index(&self, x: usize, y: usize, z: usize) -> usize{
+        (z * self.y_size + y) * self.x_size + x
+    }
+}
+
+pub struct IsoSamples3d{
+    samples: Vec<(IsoSample3d, usize, usize, usize)>,
+    x_size: usize,
+    y_size: usize,
+}
+impl Iterator for IsoSamples3d {
+    type Item = (IsoSample3d, usize, usize, usize);
+    fn next(&mut self) -> Option<Self::Item>{
+        self.samples.pop()
+    }
+}
+
+impl IsoSamples3d {
+    ///Meshes the surface where the field crosses the iso level, facing away from
+    ///the solid side. Normals are smoothed over the triangles sharing a vertex.
+    ///The surface is open where it meets the sides of the field
+    pub fn build_mesh(self, settings: &IsoMeshSettings) -> Mesh {
+        info!("Building 3d Mesh {settings:?}");
+        let IsoMeshSettings{ iso_distance, iso_level, invert, interpolation, uv_mode, .. } = *settings;
+        let field_size = Vec2::new(
+            self.x_size.saturating_sub(1) as f32,
+            self.y_size.saturating_sub(1) as f32,
+        ).max(Vec2::splat(f32::EPSILON));
+        // vertices by the grid corner at the low end of their edge and the edge axis
+        let mut used_indices = HashMap::<(usize, usize, usize, usize), u32>::new();
+        let mut vertexes = Vec::<Vec3>::new();
+        let mut indices = Vec::<u32>::new();
+
+        for (sample, x, y, z) in self{
+            let origin = Vec3::new(x as f32, y as f32, z as f32);
+            for edge in CUBE_TABLE[sample.to_case(iso_level, invert) as usize]{
+                if edge == -1 {
+                    break;
+                }
+                let (a, b) = EDGES[edge as usize];
+                let [dx, dy, dz] = CORNERS[a];
+                let axis = (0..3).find(|axis| CORNERS[a][*axis] != CORNERS[b][*axis]).unwrap();
+                let indice = *used_indices.entry((x + dx, y + dy, z + dz, axis)).or_insert_with(|| {
+                    let vertex = sample.edge_vertex(a, b, iso_level, interpolation);
+                    vertexes.push((origin + vertex) * iso_distance);
+                    vertexes.len() as u32 - 1
+                });
+                indices.push(indice);
+            }
+        }
+
+        let mut normals = vec![Vec3::ZERO; vertexes.len()];
+        for tri in indices.chunks_exact(3){
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|index| vertexes[index as usize]);
+            // not normalized, so bigger triangles weigh more
+            let normal = (b - a).cross(c - a);
+            for index in tri{
+                normals[*index as usize] += normal;
+            }
+        }
+        let normals: Vec<Vec3> = normals.into_iter().map(|normal| normal.normalize_or_zero()).collect();
+        let uvs: Vec<Vec2> = vertexes.iter().map(|vertex| match uv_mode {
+            IsoUvMode::World{ scale } => Vec2::new(vertex.x, -vertex.y) / scale,
+            // no contour to measure from in 3d, so it falls back to the field uvs
+            IsoUvMode::Field | IsoUvMode::EdgeDistance{ .. } => {
+                let uv = vertex.truncate() / (field_size * iso_distance);
+                Vec2::new(uv.x, 1.0 - uv.y)
+            }
+        }).collect();
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vertexes)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_indices(Indices::U32(indices))
+    }
+}
+
+///The 8 corner values of a cube of samples, in CORNERS order
+pub struct IsoSample3d([f32; 8]);
+impl IsoSample3d{
+    ///The solid corners as bits, see IsoSample::to_case
+    pub fn to_case(&self, iso_level: f32, invert: bool) -> u8{
+        let mut out = 0;
+        for (i, f) in self.0.iter().enumerate(){
+            if is_solid(*f, iso_level, invert) {
+                out |= 1 << i;
+            }
+        }
+        out
+    }
+
+    // where the iso level crosses the edge from corner `a` to corner `b`, in cube units
+    fn edge_vertex(&self, a: usize, b: usize, iso_level: f32, interpolation: IsoInterpolation) -> Vec3{
+        let t = IsoSample::crossing(self.0[a], self.0[b], iso_level, interpolation);
+        let [a, b] = [a, b].map(|corner| Vec3::from(CORNERS[corner].map(|d| d as f32)));
+        a + (b - a) * t
+    }
+}
+
+// Offset of each cube corner, corner i is bit 1 << i of the case.
+// The bottom face (z = 0) counter clockwise, then the top face
+const CORNERS: [[usize; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [1, 1, 0],
+    [0, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [1, 1, 1],
+    [0, 1, 1],
+];
+
+// The corners of every cube edge, the lower one first
+const EDGES: [(usize, usize); 12] = [
+    (0, 1), (3, 2), (0, 3), (1, 2),
+    (4, 5), (7, 6), (4, 7), (5, 6),
+    (0, 4), (1, 5), (3, 7), (2, 6),
+];
+
+// Indexed by the case returned from `IsoSample3d::to_case`, up to 5 triangles of
+// EDGES indices ended by -1, counter clockwise seen from the empty side.
+// Generated by walking the marching squares contour of every cube face and
+// triangulating the loops, ambiguous faces always keep their solid corners apart
+// so both cubes sharing a face agree and the surface has no holes. No diagonal
+// joins two vertices on the same cube face, the cube on the other side could
+// lay its own triangles over it
+const CUBE_TABLE: [[i8; 16]; 256] = [
+    [-1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [3, 0, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 9, 2, 2, 9, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [11, 1, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 2, 11, 1, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [11, 1, 9, 9, 1, 0, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 9, 2, 2, 9, 1, 1, 9, 11, -1, -1, -1, -1, -1, -1, -1],
+    [2, 1, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 10, 10, 0, 1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 1, 10, 3, 0, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 9, 10, 10, 9, 1, 1, 9, 3, -1, -1, -1, -1, -1, -1, -1],
+    [2, 3, 10, 10, 3, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 10, 10, 0, 11, 11, 0, 3, -1, -1, -1, -1, -1, -1, -1],
+    [2, 0, 10, 10, 0, 11, 11, 0, 9, -1, -1, -1, -1, -1, -1, -1],
+    [8, 9, 10, 10, 9, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 2, 2, 4, 0, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 8, 3, 0, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 2, 2, 4, 3, 3, 4, 9, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 8, 11, 1, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 2, 2, 4, 0, 11, 1, 3, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 8, 11, 1, 9, 9, 1, 0, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 2, 2, 4, 1, 1, 4, 11, 11, 4, 9, -1, -1, -1, -1],
+    [6, 4, 8, 2, 1, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 10, 10, 4, 1, 1, 4, 0, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 8, 2, 1, 10, 3, 0, 9, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 10, 10, 4, 1, 1, 4, 3, 3, 4, 9, -1, -1, -1, -1],
+    [6, 4, 8, 2, 3, 10, 10, 3, 11, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 10, 10, 4, 11, 11, 4, 3, 3, 4, 0, -1, -1, -1, -1],
+    [6, 4, 8, 2, 0, 10, 10, 0, 11, 11, 0, 9, -1, -1, -1, -1],
+    [6, 4, 10, 10, 4, 11, 11, 4, 9, -1, -1, -1, -1, -1, -1, -1],
+    [9, 4, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 2, 9, 4, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [3, 0, 7, 7, 0, 4, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 4, 2, 2, 4, 3, 3, 4, 7, -1, -1, -1, -1, -1, -1, -1],
+    [11, 1, 3, 9, 4, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 2, 11, 1, 3, 9, 4, 7, -1, -1, -1, -1, -1, -1, -1],
+    [11, 1, 7, 7, 1, 4, 4, 1, 0, -1, -1, -1, -1, -1, -1, -1],
+    [8, 4, 2, 2, 4, 1, 1, 4, 11, 11, 4, 7, -1, -1, -1, -1],
+    [2, 1, 10, 9, 4, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 10, 10, 0, 1, 9, 4, 7, -1, -1, -1, -1, -1, -1, -1],
+    [2, 1, 10, 3, 0, 7, 7, 0, 4, -1, -1, -1, -1, -1, -1, -1],
+    [8, 4, 10, 10, 4, 1, 1, 4, 3, 3, 4, 7, -1, -1, -1, -1],
+    [2, 3, 10, 10, 3, 11, 9, 4, 7, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 10, 10, 0, 11, 11, 0, 3, 9, 4, 7, -1, -1, -1, -1],
+    [2, 0, 10, 10, 0, 11, 11, 0, 7, 7, 0, 4, -1, -1, -1, -1],
+    [8, 4, 10, 10, 4, 11, 11, 4, 7, -1, -1, -1, -1, -1, -1, -1],
+    [6, 7, 8, 8, 7, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 7, 2, 2, 7, 0, 0, 7, 9, -1, -1, -1, -1, -1, -1, -1],
+    [6, 7, 8, 8, 7, 0, 0, 7, 3, -1, -1, -1, -1, -1, -1, -1],
+    [6, 7, 2, 2, 7, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 7, 8, 8, 7, 9, 11, 1, 3, -1, -1, -1, -1, -1, -1, -1],
+    [6, 7, 2, 2, 7, 0, 0, 7, 9, 11, 1, 3, -1, -1, -1, -1],
+    [6, 7, 8, 8, 7, 0, 0, 7, 1, 1, 7, 11, -1, -1, -1, -1],
+    [6, 7, 2, 2, 7, 1, 1, 7, 11, -1, -1, -1, -1, -1, -1, -1],
+    [6, 7, 8, 8, 7, 9, 2, 1, 10, -1, -1, -1, -1, -1, -1, -1],
+    [6, 7, 10, 10, 7, 1, 1, 7, 0, 0, 7, 9, -1, -1, -1, -1],
+    [6, 7, 8, 8, 7, 0, 0, 7, 3, 2, 1, 10, -1, -1, -1, -1],
+    [6, 7, 10, 10, 7, 1, 1, 7, 3, -1, -1, -1, -1, -1, -1, -1],
+    [6, 7, 8, 8, 7, 9, 2, 3, 10, 10, 3, 11, -1, -1, -1, -1],
+    [6, 7, 10, 10, 0, 11, 11, 0, 3, 10, 7, 0, 0, 7, 9, -1],
+    [6, 7, 8, 8, 7, 0, 0, 7, 2, 2, 7, 10, 10, 7, 11, -1],
+    [6, 7, 10, 10, 7, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [7, 5, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 2, 7, 5, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [3, 0, 9, 7, 5, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 9, 2, 2, 9, 3, 7, 5, 11, -1, -1, -1, -1, -1, -1, -1],
+    [7, 5, 3, 3, 5, 1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 2, 7, 5, 3, 3, 5, 1, -1, -1, -1, -1, -1, -1, -1],
+    [7, 5, 9, 9, 5, 0, 0, 5, 1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 9, 2, 2, 9, 1, 1, 9, 5, 5, 9, 7, -1, -1, -1, -1],
+    [2, 1, 10, 7, 5, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 10, 10, 0, 1, 7, 5, 11, -1, -1, -1, -1, -1, -1, -1],
+    [2, 1, 10, 3, 0, 9, 7, 5, 11, -1, -1, -1, -1, -1, -1, -1],
+    [8, 9, 10, 10, 9, 1, 1, 9, 3, 7, 5, 11, -1, -1, -1, -1],
+    [2, 3, 10, 10, 3, 5, 5, 3, 7, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 10, 10, 0, 5, 5, 0, 7, 7, 0, 3, -1, -1, -1, -1],
+    [2, 0, 10, 10, 0, 5, 5, 0, 7, 7, 0, 9, -1, -1, -1, -1],
+    [8, 9, 10, 10, 9, 5, 5, 9, 7, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 8, 7, 5, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 2, 2, 4, 0, 7, 5, 11, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 8, 3, 0, 9, 7, 5, 11, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 2, 2, 4, 3, 3, 4, 9, 7, 5, 11, -1, -1, -1, -1],
+    [6, 4, 8, 7, 5, 3, 3, 5, 1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 2, 2, 4, 0, 7, 5, 3, 3, 5, 1, -1, -1, -1, -1],
+    [6, 4, 8, 7, 5, 9, 9, 5, 0, 0, 5, 1, -1, -1, -1, -1],
+    [6, 4, 2, 2, 4, 1, 1, 9, 5, 5, 9, 7, 1, 4, 9, -1],
+    [6, 4, 8, 2, 1, 10, 7, 5, 11, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 10, 10, 4, 1, 1, 4, 0, 7, 5, 11, -1, -1, -1, -1],
+    [6, 4, 8, 2, 1, 10, 3, 0, 9, 7, 5, 11, -1, -1, -1, -1],
+    [6, 4, 10, 10, 4, 1, 1, 4, 3, 3, 4, 9, 7, 5, 11, -1],
+    [6, 4, 8, 2, 3, 10, 10, 3, 5, 5, 3, 7, -1, -1, -1, -1],
+    [6, 4, 10, 10, 3, 5, 5, 3, 7, 10, 4, 3, 3, 4, 0, -1],
+    [6, 4, 8, 2, 0, 10, 10, 0, 5, 5, 0, 7, 7, 0, 9, -1],
+    [6, 4, 10, 10, 9, 5, 5, 9, 7, 10, 4, 9, -1, -1, -1, -1],
+    [9, 4, 11, 11, 4, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 2, 9, 4, 11, 11, 4, 5, -1, -1, -1, -1, -1, -1, -1],
+    [3, 0, 11, 11, 0, 5, 5, 0, 4, -1, -1, -1, -1, -1, -1, -1],
+    [8, 4, 2, 2, 4, 3, 3, 4, 11, 11, 4, 5, -1, -1, -1, -1],
+    [9, 4, 3, 3, 4, 1, 1, 4, 5, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 2, 9, 4, 3, 3, 4, 1, 1, 4, 5, -1, -1, -1, -1],
+    [4, 5, 0, 0, 5, 1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 4, 2, 2, 4, 1, 1, 4, 5, -1, -1, -1, -1, -1, -1, -1],
+    [2, 1, 10, 9, 4, 11, 11, 4, 5, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 10, 10, 0, 1, 9, 4, 11, 11, 4, 5, -1, -1, -1, -1],
+    [2, 1, 10, 3, 0, 11, 11, 0, 5, 5, 0, 4, -1, -1, -1, -1],
+    [8, 4, 10, 10, 4, 1, 1, 4, 3, 3, 4, 11, 11, 4, 5, -1],
+    [2, 3, 10, 10, 3, 5, 5, 3, 4, 4, 3, 9, -1, -1, -1, -1],
+    [8, 0, 10, 10, 0, 5, 5, 3, 4, 4, 3, 9, 5, 0, 3, -1],
+    [2, 0, 10, 10, 0, 5, 5, 0, 4, -1, -1, -1, -1, -1, -1, -1],
+    [8, 4, 10, 10, 4, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 5, 8, 8, 5, 9, 9, 5, 11, -1, -1, -1, -1, -1, -1, -1],
+    [6, 5, 2, 2, 5, 0, 0, 5, 9, 9, 5, 11, -1, -1, -1, -1],
+    [6, 5, 8, 8, 5, 0, 0, 5, 3, 3, 5, 11, -1, -1, -1, -1],
+    [6, 5, 2, 2, 5, 3, 3, 5, 11, -1, -1, -1, -1, -1, -1, -1],
+    [6, 5, 8, 8, 5, 9, 9, 5, 3, 3, 5, 1, -1, -1, -1, -1],
+    [6, 5, 2, 2, 5, 0, 0, 5, 9, 9, 5, 3, 3, 5, 1, -1],
+    [6, 5, 8, 8, 5, 0, 0, 5, 1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 5, 2, 2, 5, 1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 5, 8, 8, 5, 9, 9, 5, 11, 2, 1, 10, -1, -1, -1, -1],
+    [6, 0, 10, 10, 0, 1, 6, 5, 0, 0, 5, 9, 9, 5, 11, -1],
+    [6, 5, 8, 8, 5, 0, 0, 5, 3, 3, 5, 11, 2, 1, 10, -1],
+    [6, 3, 10, 10, 3, 1, 6, 5, 3, 3, 5, 11, -1, -1, -1, -1],
+    [6, 5, 8, 8, 5, 9, 9, 5, 3, 3, 5, 2, 2, 5, 10, -1],
+    [6, 5, 10, 9, 0, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 5, 8, 8, 5, 0, 0, 5, 2, 2, 5, 10, -1, -1, -1, -1],
+    [6, 5, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, 8, 0, 2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, 3, 0, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, 8, 9, 2, 2, 9, 3, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, 11, 1, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, 8, 0, 2, 11, 1, 3, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, 11, 1, 9, 9, 1, 0, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, 8, 9, 2, 2, 9, 1, 1, 9, 11, -1, -1, -1, -1],
+    [2, 1, 6, 6, 1, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 6, 6, 0, 5, 5, 0, 1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 1, 6, 6, 1, 5, 3, 0, 9, -1, -1, -1, -1, -1, -1, -1],
+    [8, 9, 6, 6, 9, 5, 5, 9, 1, 1, 9, 3, -1, -1, -1, -1],
+    [2, 3, 6, 6, 3, 5, 5, 3, 11, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 6, 6, 0, 5, 5, 0, 11, 11, 0, 3, -1, -1, -1, -1],
+    [2, 0, 6, 6, 0, 5, 5, 0, 11, 11, 0, 9, -1, -1, -1, -1],
+    [8, 9, 6, 6, 9, 5, 5, 9, 11, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 8, 8, 5, 4, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 2, 2, 5, 0, 0, 5, 4, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 8, 8, 5, 4, 3, 0, 9, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 2, 2, 5, 3, 3, 5, 9, 9, 5, 4, -1, -1, -1, -1],
+    [10, 5, 8, 8, 5, 4, 11, 1, 3, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 2, 2, 5, 0, 0, 5, 4, 11, 1, 3, -1, -1, -1, -1],
+    [10, 5, 8, 8, 5, 4, 11, 1, 9, 9, 1, 0, -1, -1, -1, -1],
+    [10, 5, 2, 2, 9, 1, 1, 9, 11, 2, 5, 9, 9, 5, 4, -1],
+    [2, 1, 8, 8, 1, 4, 4, 1, 5, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 4, 4, 1, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 1, 8, 8, 1, 4, 4, 1, 5, 3, 0, 9, -1, -1, -1, -1],
+    [3, 1, 9, 9, 1, 4, 4, 1, 5, -1, -1, -1, -1, -1, -1, -1],
+    [2, 3, 8, 8, 3, 4, 4, 3, 5, 5, 3, 11, -1, -1, -1, -1],
+    [11, 5, 3, 3, 5, 0, 0, 5, 4, -1, -1, -1, -1, -1, -1, -1],
+    [2, 5, 8, 8, 5, 4, 2, 0, 5, 5, 0, 11, 11, 0, 9, -1],
+    [11, 5, 9, 9, 5, 4, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, 9, 4, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, 8, 0, 2, 9, 4, 7, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, 3, 0, 7, 7, 0, 4, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, 8, 4, 2, 2, 4, 3, 3, 4, 7, -1, -1, -1, -1],
+    [10, 5, 6, 11, 1, 3, 9, 4, 7, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, 8, 0, 2, 11, 1, 3, 9, 4, 7, -1, -1, -1, -1],
+    [10, 5, 6, 11, 1, 7, 7, 1, 4, 4, 1, 0, -1, -1, -1, -1],
+    [10, 5, 6, 8, 4, 2, 2, 4, 1, 1, 4, 11, 11, 4, 7, -1],
+    [2, 1, 6, 6, 1, 5, 9, 4, 7, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 6, 6, 0, 5, 5, 0, 1, 9, 4, 7, -1, -1, -1, -1],
+    [2, 1, 6, 6, 1, 5, 3, 0, 7, 7, 0, 4, -1, -1, -1, -1],
+    [8, 1, 6, 6, 1, 5, 8, 4, 1, 1, 4, 3, 3, 4, 7, -1],
+    [2, 3, 6, 6, 3, 5, 5, 3, 11, 9, 4, 7, -1, -1, -1, -1],
+    [8, 0, 6, 6, 0, 5, 5, 0, 11, 11, 0, 3, 9, 4, 7, -1],
+    [2, 0, 6, 6, 0, 5, 5, 0, 11, 11, 0, 7, 7, 0, 4, -1],
+    [8, 11, 6, 6, 11, 5, 8, 4, 11, 11, 4, 7, -1, -1, -1, -1],
+    [10, 5, 8, 8, 5, 9, 9, 5, 7, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 2, 2, 5, 0, 0, 5, 9, 9, 5, 7, -1, -1, -1, -1],
+    [10, 5, 8, 8, 5, 0, 0, 5, 3, 3, 5, 7, -1, -1, -1, -1],
+    [10, 5, 2, 2, 5, 3, 3, 5, 7, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 8, 8, 5, 9, 9, 5, 7, 11, 1, 3, -1, -1, -1, -1],
+    [10, 5, 2, 2, 5, 0, 0, 5, 9, 9, 5, 7, 11, 1, 3, -1],
+    [10, 5, 8, 8, 5, 0, 0, 7, 1, 1, 7, 11, 0, 5, 7, -1],
+    [10, 5, 2, 2, 7, 1, 1, 7, 11, 2, 5, 7, -1, -1, -1, -1],
+    [2, 1, 8, 8, 1, 9, 9, 1, 7, 7, 1, 5, -1, -1, -1, -1],
+    [9, 0, 7, 7, 0, 5, 5, 0, 1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 1, 8, 8, 7, 0, 0, 7, 3, 8, 1, 7, 7, 1, 5, -1],
+    [3, 1, 7, 7, 1, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 3, 8, 8, 5, 9, 9, 5, 7, 8, 3, 5, 5, 3, 11, -1],
+    [11, 5, 3, 3, 5, 0, 0, 5, 9, 9, 5, 7, -1, -1, -1, -1],
+    [2, 0, 8, 11, 5, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [11, 5, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 11, 6, 6, 11, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 11, 6, 6, 11, 7, 8, 0, 2, -1, -1, -1, -1, -1, -1, -1],
+    [10, 11, 6, 6, 11, 7, 3, 0, 9, -1, -1, -1, -1, -1, -1, -1],
+    [10, 11, 6, 6, 11, 7, 8, 9, 2, 2, 9, 3, -1, -1, -1, -1],
+    [10, 1, 6, 6, 1, 7, 7, 1, 3, -1, -1, -1, -1, -1, -1, -1],
+    [10, 1, 6, 6, 1, 7, 7, 1, 3, 8, 0, 2, -1, -1, -1, -1],
+    [10, 1, 6, 6, 1, 7, 7, 1, 9, 9, 1, 0, -1, -1, -1, -1],
+    [10, 1, 6, 6, 1, 7, 7, 1, 9, 9, 1, 8, 8, 1, 2, -1],
+    [2, 1, 6, 6, 1, 7, 7, 1, 11, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 6, 6, 0, 7, 7, 0, 11, 11, 0, 1, -1, -1, -1, -1],
+    [2, 1, 6, 6, 1, 7, 7, 1, 11, 3, 0, 9, -1, -1, -1, -1],
+    [8, 9, 6, 6, 1, 7, 7, 1, 11, 6, 9, 1, 1, 9, 3, -1],
+    [2, 3, 6, 6, 3, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 6, 6, 0, 7, 7, 0, 3, -1, -1, -1, -1, -1, -1, -1],
+    [2, 0, 6, 6, 0, 7, 7, 0, 9, -1, -1, -1, -1, -1, -1, -1],
+    [8, 9, 6, 6, 9, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 11, 8, 8, 11, 4, 4, 11, 7, -1, -1, -1, -1, -1, -1, -1],
+    [10, 11, 2, 2, 11, 0, 0, 11, 4, 4, 11, 7, -1, -1, -1, -1],
+    [10, 11, 8, 8, 11, 4, 4, 11, 7, 3, 0, 9, -1, -1, -1, -1],
+    [10, 11, 2, 2, 4, 3, 3, 4, 9, 2, 11, 4, 4, 11, 7, -1],
+    [10, 1, 8, 8, 1, 4, 4, 1, 7, 7, 1, 3, -1, -1, -1, -1],
+    [10, 4, 2, 2, 4, 0, 10, 1, 4, 4, 1, 7, 7, 1, 3, -1],
+    [10, 1, 8, 8, 1, 4, 4, 1, 7, 7, 1, 9, 9, 1, 0, -1],
+    [10, 1, 2, 7, 4, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 1, 8, 8, 1, 4, 4, 1, 7, 7, 1, 11, -1, -1, -1, -1],
+    [7, 4, 11, 11, 4, 1, 1, 4, 0, -1, -1, -1, -1, -1, -1, -1],
+    [2, 1, 8, 8, 1, 4, 4, 1, 7, 7, 1, 11, 3, 0, 9, -1],
+    [3, 1, 9, 9, 1, 4, 4, 1, 7, 7, 1, 11, -1, -1, -1, -1],
+    [2, 3, 8, 8, 3, 4, 4, 3, 7, -1, -1, -1, -1, -1, -1, -1],
+    [7, 4, 3, 3, 4, 0, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 7, 8, 8, 7, 4, 2, 0, 7, 7, 0, 9, -1, -1, -1, -1],
+    [7, 4, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 11, 6, 6, 11, 4, 4, 11, 9, -1, -1, -1, -1, -1, -1, -1],
+    [10, 11, 6, 6, 11, 4, 4, 11, 9, 8, 0, 2, -1, -1, -1, -1],
+    [10, 11, 6, 6, 11, 4, 4, 11, 0, 0, 11, 3, -1, -1, -1, -1],
+    [10, 11, 6, 6, 11, 4, 4, 11, 8, 8, 11, 2, 2, 11, 3, -1],
+    [10, 1, 6, 6, 1, 4, 4, 1, 9, 9, 1, 3, -1, -1, -1, -1],
+    [10, 1, 6, 6, 1, 4, 4, 1, 9, 9, 1, 3, 8, 0, 2, -1],
+    [10, 1, 6, 6, 1, 4, 4, 1, 0, -1, -1, -1, -1, -1, -1, -1],
+    [10, 1, 6, 6, 1, 4, 4, 1, 8, 8, 1, 2, -1, -1, -1, -1],
+    [2, 1, 6, 6, 1, 4, 4, 1, 9, 9, 1, 11, -1, -1, -1, -1],
+    [8, 0, 6, 6, 11, 4, 4, 11, 9, 6, 0, 11, 11, 0, 1, -1],
+    [2, 1, 6, 6, 1, 4, 4, 11, 0, 0, 11, 3, 4, 1, 11, -1],
+    [8, 4, 6, 3, 1, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 3, 6, 6, 3, 4, 4, 3, 9, -1, -1, -1, -1, -1, -1, -1],
+    [8, 0, 6, 6, 3, 4, 4, 3, 9, 6, 0, 3, -1, -1, -1, -1],
+    [2, 0, 6, 6, 0, 4, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 4, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 11, 8, 8, 11, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 11, 2, 2, 11, 0, 0, 11, 9, -1, -1, -1, -1, -1, -1, -1],
+    [10, 11, 8, 8, 11, 0, 0, 11, 3, -1, -1, -1, -1, -1, -1, -1],
+    [10, 11, 2, 2, 11, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 1, 8, 8, 1, 9, 9, 1, 3, -1, -1, -1, -1, -1, -1, -1],
+    [10, 9, 2, 2, 9, 0, 10, 1, 9, 9, 1, 3, -1, -1, -1, -1],
+    [10, 1, 8, 8, 1, 0, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 1, 2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 1, 8, 8, 1, 9, 9, 1, 11, -1, -1, -1, -1, -1, -1, -1],
+    [9, 0, 11, 11, 0, 1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 1, 8, 8, 11, 0, 0, 11, 3, 8, 1, 11, -1, -1, -1, -1],
+    [3, 1, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 3, 8, 8, 3, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [9, 0, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 0, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+];
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::noise::{hash, unit};
+
+    #[test]
+    fn random_fields_are_manifold(){
+        let settings = IsoMeshSettings{ iso_level: 0.5, ..default() };
+        for seed in 0..1000{
+            let values = (0..64).map(|i| unit(hash(i, 0, seed))).collect();
+            let mesh = IsoField3d::new_from((4, 4, 4), values).sample_all().build_mesh(&settings);
+            let Some(Indices::U32(indices)) = mesh.indices() else { panic!("no indices") };
+            // two triangles on the same directed edge overlap or fold over each other
+            let mut edges = HashMap::new();
+            for tri in indices.chunks_exact(3){
+                for i in 0..3{
+                    let edge = (tri[i], tri[(i + 1) % 3]);
+                    assert!(edges.insert(edge, ()).is_none(), "seed {seed} uses the edge {edge:?} twice");
+                }
+            }
+        }
+    }
+    // a ball of radius 3 in the middle of a 10 × 10 × 10 field, clear of the sides
+    fn ball() -> IsoField3d{
+        let mut field = IsoField3d::new((10, 10, 10));
+        for z in 0..10{
+            for y in 0..10{
+                for x in 0..10{
+                    field.set(x, y, z, 4.0 - Vec3::new(x as f32, y as f32, z as f32).distance(Vec3::splat(4.5)));
+                }
+            }
+        }
+        field
+    }
+
+    fn triangles(mesh: &Mesh) -> Vec<[u32; 3]>{
+        let Some(Indices::U32(indices)) = mesh.indices() else { panic!("no indices") };
+        indices.chunks_exact(3).map(|tri| [tri[0], tri[1], tri[2]]).collect()
+    }
+
+    #[test]
+    fn ball_is_closed(){
+        for interpolation in [IsoInterpolation::Midpoint, IsoInterpolation::Linear]{
+            for invert in [false, true]{
+                let settings = IsoMeshSettings{ interpolation, invert, ..default() };
+                let mesh = ball().sample_all().build_mesh(&settings);
+                let triangles = triangles(&mesh);
+                assert!(!triangles.is_empty());
+                let mut edges = HashMap::new();
+                for tri in &triangles{
+                    for i in 0..3{
+                        edges.insert((tri[i], tri[(i + 1) % 3]), ());
+                    }
+                }
+                for (a, b) in edges.keys(){
+                    assert!(edges.contains_key(&(*b, *a)), "{interpolation:?} invert {invert}: the edge {a} {b} is open");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ball_faces_out(){
+        let center = Vec3::splat(4.5 * 0.5);
+        for invert in [false, true]{
+            let settings = IsoMeshSettings{ interpolation: IsoInterpolation::Linear, invert, iso_distance: 0.5, ..default() };
+            let mesh = ball().sample_all().build_mesh(&settings);
+            let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).and_then(|positions| positions.as_float3()).unwrap();
+            let normals = mesh.attribute(Mesh::ATTRIBUTE_NORMAL).and_then(|normals| normals.as_float3()).unwrap();
+            // inverted, the solid side is outside of the ball
+            let outwards = if invert { -1.0 } else { 1.0 };
+            for tri in triangles(&mesh){
+                let [a, b, c] = tri.map(|index| Vec3::from(positions[index as usize]));
+                let middle = (a + b + c) / 3.0;
+                assert!((b - a).cross(c - a).dot(middle - center) * outwards > 0.0, "invert {invert}: {tri:?}");
+            }
+            for (position, normal) in positions.iter().zip(normals){
+                let out = Vec3::from(*position) - center;
+                assert!(Vec3::from(*normal).dot(out) * outwards > 0.0, "invert {invert}: {position:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{IsoField, IsoFieldAsset};
+
+///The part of each pixel that becomes the sample value
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IsoImageChannel{
+    ///The grayscale value, the only channel of gray images
+    #[default]
+    Luminance,
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+///Settings of the IsoImageLoader, set them in the `.meta` file of the image or with
+///`AssetServer::load_with_settings`. The channel goes from `min` at 0 to `max` at full
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct IsoImageSettings{
+    pub channel: IsoImageChannel,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for IsoImageSettings{
+    fn default() -> Self{
+        Self{ channel: IsoImageChannel::Luminance, min: 0.0, max: 1.0 }
+    }
+}
+
+#[derive(Debug)]
+pub enum IsoImageError{
+    Io(std::io::Error),
+    Image(image::ImageError),
+}
+
+impl std::fmt::Display for IsoImageError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        match self {
+            Self::Io(error) => write!(f, "could not read image: {error}"),
+            Self::Image(error) => write!(f, "could not decode image: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for IsoImageError{}
+
+impl From<std::io::Error> for IsoImageError{
+    fn from(error: std::io::Error) -> Self{
+        Self::Io(error)
+    }
+}
+
+impl From<image::ImageError> for IsoImageError{
+    fn from(error: image::ImageError) -> Self{
+        Self::Image(error)
+    }
+}
+
+///Loads PNG and PGM (or any PNM) images as an IsoFieldAsset, one sample per pixel.
+///The top row of the image is the top row of the field.
+///Only files named like `map.iso.png`, `map.iso.pgm` or `map.iso.pnm` are claimed, so the
+///image loader of Bevy keeps handling every other image. Saving the image rebuilds the mesh
+///when the `file_watcher` feature of Bevy is on
+#[derive(Default)]
+pub struct IsoImageLoader;
+
+impl AssetLoader for IsoImageLoader{
+    type Asset = IsoFieldAsset;
+    type Settings = IsoImageSettings;
+    type Error = IsoImageError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        settings: &'a IsoImageSettings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<IsoFieldAsset, IsoImageError>{
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let image = image::load_from_memory(&bytes)?;
+        Ok(IsoFieldAsset::new(IsoField::from_image(&image, settings)))
+    }
+
+    fn extensions(&self) -> &[&str]{
+        &["iso.png", "iso.pgm", "iso.pnm"]
+    }
+}
+
+impl IsoField{
+    ///A field with one sample per pixel of `image`, see IsoImageSettings
+    pub fn from_image(image: &DynamicImage, settings: &IsoImageSettings) -> Self{
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let channel = match settings.channel {
+            IsoImageChannel::Luminance => None,
+            IsoImageChannel::Red => Some(0),
+            IsoImageChannel::Green => Some(1),
+            IsoImageChannel::Blue => Some(2),
+            IsoImageChannel::Alpha => Some(3),
+        };
+        let pixels: Vec<f32> = match channel {
+            None => image.to_luma32f().into_raw(),
+            Some(channel) => image.to_rgba32f().pixels().map(|pixel| pixel.0[channel]).collect(),
+        };
+        let mut field = Vec::with_capacity(width * height);
+        // images go top down, the field goes bottom up
+        for row in pixels.chunks_exact(width.max(1)).rev(){
+            field.extend(row.iter().map(|value| settings.min + (settings.max - settings.min) * value));
+        }
+        Self::new_from((width, height), field)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use bevy::prelude::default;
+    use image::{GrayImage, Luma, Rgba, RgbaImage};
+
+    #[test]
+    fn rows_are_flipped(){
+        // 2 × 3, brighter towards the bottom of the image
+        let image = DynamicImage::ImageLuma8(GrayImage::from_fn(2, 3, |x, y| Luma([(y * 100 + x * 10) as u8])));
+        let field = IsoField::from_image(&image, &IsoImageSettings::default());
+        assert_eq!((field.width(), field.height()), (2, 3));
+        for y in 0..3{
+            for x in 0..2{
+                let pixel = ((2 - y) * 100 + x * 10) as f32 / 255.0;
+                assert!((field.get(x, y) - pixel).abs() < 1e-6, "({x}, {y}): {}", field.get(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn channels(){
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([255, 51, 102, 0])));
+        for (channel, expected) in [
+            (IsoImageChannel::Red, 1.0),
+            (IsoImageChannel::Green, 0.2),
+            (IsoImageChannel::Blue, 0.4),
+            (IsoImageChannel::Alpha, 0.0),
+        ]{
+            let field = IsoField::from_image(&image, &IsoImageSettings{ channel, ..default() });
+            assert!((field.get(0, 0) - expected).abs() < 1e-6, "{channel:?}: {}", field.get(0, 0));
+        }
+        // luminance weighs the colors, it's none of them alone
+        let luminance = IsoField::from_image(&image, &IsoImageSettings::default()).get(0, 0);
+        assert!(luminance > 0.2 && luminance < 1.0, "{luminance}");
+    }
+
+    #[test]
+    fn min_and_max(){
+        let image = DynamicImage::ImageLuma8(GrayImage::from_fn(3, 1, |x, _| Luma([[0, 51, 255][x as usize]])));
+        let field = IsoField::from_image(&image, &IsoImageSettings{ min: -1.0, max: 3.0, ..default() });
+        for (value, expected) in field.values().iter().zip([-1.0, -0.2, 3.0]){
+            assert!((value - expected).abs() < 1e-5, "{:?}", field.values());
+        }
+        // max below min flips the image
+        let field = IsoField::from_image(&image, &IsoImageSettings{ min: 1.0, max: 0.0, ..default() });
+        assert_eq!((field.get(0, 0), field.get(2, 0)), (1.0, 0.0));
+    }
+
+    #[test]
+    fn only_iso_extensions(){
+        let extensions = IsoImageLoader.extensions();
+        for plain in ["png", "pgm", "pnm", "jpg"]{
+            assert!(!extensions.contains(&plain), "{plain} is claimed");
+        }
+        assert!(extensions.iter().all(|extension| extension.starts_with("iso.")));
+        assert!(image::load_from_memory(b"not an image").is_err());
+    }
+}
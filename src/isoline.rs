@@ -0,0 +1,272 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::{tri_index_to_vertex, IsoField, IsoMeshSettings, IsoSample, IsoSamples};
+
+///A contour of the IsoField, with the inside always on the left
+///so outer loops wind counter clockwise and holes clockwise
+#[derive(Debug, Clone, PartialEq)]
+pub struct IsoLine{
+    pub points: Vec<Vec2>,
+    ///The last point connects back to the first
+    pub closed: bool,
+}
+
+impl IsoLine{
+    ///Positive for counter clockwise loops
+    pub fn signed_area(&self) -> f32{
+        if !self.closed {
+            return 0.0;
+        }
+        let mut area = 0.0;
+        for (i, a) in self.points.iter().enumerate(){
+            let b = self.points[(i + 1) % self.points.len()];
+            area += a.perp_dot(b);
+        }
+        area / 2.0
+    }
+
+    ///A closed loop around an empty region
+    pub fn is_hole(&self) -> bool{
+        self.signed_area() < 0.0
+    }
+
+    ///Moves the line from field coordinates into world space
+    pub fn to_world(&self, iso_distance: f32, transform: &GlobalTransform) -> IsoLine{
+        IsoLine{
+            points: self.points
+                .iter()
+                .map(|point| transform.transform_point((*point * iso_distance).extend(0.0)).truncate())
+                .collect(),
+            closed: self.closed,
+        }
+    }
+}
+
+// A sample edge of the field, named by its lower left corner
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+enum EdgeKey{
+    // (x, y) -> (x + 1, y)
+    Horizontal(usize, usize),
+    // (x, y) -> (x, y + 1)
+    Vertical(usize, usize),
+}
+
+// Cell sides, in the order the segment table uses them
+const LEFT: u8 = 0;
+const TOP: u8 = 1;
+const RIGHT: u8 = 2;
+const BOTTOM: u8 = 3;
+
+// Segments for each case going from side to side with the inside on the left
+const SEGMENT_TABLE: [&[(u8, u8)]; 16] = [
+    &[],
+    &[(BOTTOM, LEFT)],
+    &[(LEFT, TOP)],
+    &[(BOTTOM, TOP)],
+    &[(TOP, RIGHT)],
+    &[(TOP, LEFT), (BOTTOM, RIGHT)],
+    &[(LEFT, RIGHT)],
+    &[(BOTTOM, RIGHT)],
+    &[(RIGHT, BOTTOM)],
+    &[(RIGHT, LEFT)],
+    &[(LEFT, BOTTOM), (RIGHT, TOP)],
+    &[(RIGHT, TOP)],
+    &[(TOP, BOTTOM)],
+    &[(TOP, LEFT)],
+    &[(LEFT, BOTTOM)],
+    &[],
+];
+
+// The CASE_TABLE vertex in the middle of each side
+const SIDE_TO_TRI_INDEX: [i8; 4] = [1, 3, 5, 7];
+
+// Saddle cases 5 and 10 with the solid corners kept apart
+const SPLIT_SEGMENT_TABLE: [&[(u8, u8)]; 2] = [
+    &[(BOTTOM, LEFT), (TOP, RIGHT)],
+    &[(LEFT, TOP), (RIGHT, BOTTOM)],
+];
+
+impl IsoSample{
+    fn to_segments(&self, settings: &IsoMeshSettings) -> &'static [(u8, u8)]{
+        let IsoMeshSettings{ iso_level, invert, saddle, .. } = *settings;
+        let case = self.to_case(iso_level, invert) as usize;
+        match case {
+            5 if !self.saddle_joined(iso_level, invert, saddle) => SPLIT_SEGMENT_TABLE[0],
+            10 if !self.saddle_joined(iso_level, invert, saddle) => SPLIT_SEGMENT_TABLE[1],
+            _ => SEGMENT_TABLE[case],
+        }
+    }
+}
+
+fn side_to_edge(side: u8, x: usize, y: usize) -> EdgeKey{
+    match side {
+        LEFT => EdgeKey::Vertical(x, y),
+        TOP => EdgeKey::Horizontal(x, y + 1),
+        RIGHT => EdgeKey::Vertical(x + 1, y),
+        BOTTOM => EdgeKey::Horizontal(x, y),
+        _ => unreachable!()
+    }
+}
+
+impl IsoField{
+    ///Walks the marching squares edges and chains them into contour lines.
+    ///Points are in field coordinates (one unit per sample),
+    ///use `IsoLine::to_world` to place them in the world
+    pub fn isolines(&self, settings: &IsoMeshSettings) -> Vec<IsoLine>{
+        self.sample_all().isolines(settings)
+    }
+}
+
+impl IsoSamples{
+    ///The contour lines through these samples, see `IsoField::isolines`
+    pub fn isolines(&self, settings: &IsoMeshSettings) -> Vec<IsoLine>{
+        let mut next = HashMap::<EdgeKey, EdgeKey>::new();
+        let mut points = HashMap::<EdgeKey, Vec2>::new();
+        for (sample, x, y) in self.samples.iter(){
+            let origin = Vec2::new(*x as f32, *y as f32);
+            for (from, to) in sample.to_segments(settings){
+                for side in [*from, *to]{
+                    points.entry(side_to_edge(side, *x, *y)).or_insert_with(|| {
+                        let index = SIDE_TO_TRI_INDEX[side as usize];
+                        origin + tri_index_to_vertex(index, sample, settings.iso_level, settings.interpolation).unwrap()
+                    });
+                }
+                next.insert(side_to_edge(*from, *x, *y), side_to_edge(*to, *x, *y));
+            }
+        }
+
+        let mut lines = Vec::new();
+        // lines that leave the field can't close, start them at the border
+        let mut starts: Vec<EdgeKey> = next.keys().copied().collect();
+        let ends: HashSet<EdgeKey> = next.values().copied().collect();
+        starts.retain(|start| !ends.contains(start));
+        for start in starts{
+            lines.push(chain(start, &mut next, &points, false));
+        }
+        while let Some(start) = next.keys().next().copied(){
+            lines.push(chain(start, &mut next, &points, true));
+        }
+        lines
+    }
+}
+
+fn chain(start: EdgeKey, next: &mut HashMap<EdgeKey, EdgeKey>, points: &HashMap<EdgeKey, Vec2>, closed: bool) -> IsoLine{
+    let mut line = vec![points[&start]];
+    let mut current = start;
+    while let Some(edge) = next.remove(&current){
+        if edge == start {
+            break;
+        }
+        line.push(points[&edge]);
+        current = edge;
+    }
+    IsoLine{ points: line, closed }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::{IsoInterpolation, SaddleResolution};
+
+    // solid within `radius` of any of the centers, 11 × 11 samples
+    fn circles(centers: &[Vec2], radius: f32) -> IsoField{
+        let mut field = IsoField::new((11, 11));
+        for y in 0..11{
+            for x in 0..11{
+                let point = Vec2::new(x as f32, y as f32);
+                let distance = centers.iter().map(|center| point.distance(*center)).fold(f32::INFINITY, f32::min);
+                field.set(x, y, radius - distance);
+            }
+        }
+        field
+    }
+
+    fn settings() -> IsoMeshSettings{
+        IsoMeshSettings{ iso_level: 0.0, interpolation: IsoInterpolation::Linear, ..default() }
+    }
+
+    // a little to the left of the middle of every segment is solid, and to the right is not
+    fn assert_inside_on_left(field: &IsoField, line: &IsoLine){
+        let segments = if line.closed { line.points.len() } else { line.points.len() - 1 };
+        for i in 0..segments{
+            let (a, b) = (line.points[i], line.points[(i + 1) % line.points.len()]);
+            let left = (b - a).perp().normalize() * 0.25;
+            let middle = (a + b) * 0.5;
+            assert!(field.sample_at(middle + left).unwrap() > 0.0, "{a} -> {b} has empty on the left");
+            assert!(field.sample_at(middle - left).unwrap() <= 0.0, "{a} -> {b} has solid on the right");
+        }
+    }
+
+    #[test]
+    fn circle_is_one_loop(){
+        let field = circles(&[Vec2::splat(5.0)], 3.5);
+        let lines = field.isolines(&settings());
+        let [line] = &lines[..] else { panic!("{lines:?}") };
+        assert!(line.closed && !line.is_hole());
+        assert!((line.signed_area() - std::f32::consts::PI * 3.5 * 3.5).abs() < 1.0, "{}", line.signed_area());
+        for point in &line.points{
+            assert!((point.distance(Vec2::splat(5.0)) - 3.5).abs() < 0.1, "{point}");
+        }
+        assert_inside_on_left(&field, line);
+    }
+
+    #[test]
+    fn ring_has_a_hole(){
+        let mut field = circles(&[Vec2::splat(5.0)], 4.0);
+        for value in field.values_mut(){
+            // solid between 1.5 and 4 from the center
+            *value = value.min(2.5 - *value);
+        }
+        let lines = field.isolines(&settings());
+        assert_eq!(lines.len(), 2, "{lines:?}");
+        assert_eq!(lines.iter().filter(|line| line.closed && line.is_hole()).count(), 1);
+        assert_eq!(lines.iter().filter(|line| line.closed && !line.is_hole()).count(), 1);
+        for line in &lines{
+            assert_inside_on_left(&field, line);
+        }
+    }
+
+    #[test]
+    fn border_cuts_lines_open(){
+        let field = circles(&[Vec2::new(0.0, 2.5), Vec2::new(10.0, 7.0)], 2.0);
+        let lines = field.isolines(&settings());
+        assert_eq!(lines.len(), 2, "{lines:?}");
+        for line in &lines{
+            assert!(!line.closed && line.signed_area() == 0.0);
+            for end in [line.points[0], *line.points.last().unwrap()]{
+                assert!(end.x == 0.0 || end.x == 10.0, "{line:?} ends inside the field");
+            }
+            assert_inside_on_left(&field, line);
+        }
+    }
+
+    #[test]
+    fn saddles(){
+        // the middle cell has its bottom left and top right corners solid
+        let field = IsoField::new_from((4, 4), vec![
+            0.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 0.0,
+        ]);
+        let loops = |saddle, iso_level| {
+            let lines = field.isolines(&IsoMeshSettings{ saddle, iso_level, ..default() });
+            assert!(lines.iter().all(|line| line.closed && !line.is_hole()), "{lines:?}");
+            lines.len()
+        };
+        assert_eq!(loops(SaddleResolution::Join, 0.5), 1);
+        assert_eq!(loops(SaddleResolution::Split, 0.5), 2);
+        // the center of the saddle cell is 0.5
+        assert_eq!(loops(SaddleResolution::Decide, 0.6), 2);
+        assert_eq!(loops(SaddleResolution::Decide, 0.4), 1);
+
+        // a lone saddle cell on the border is cut into two open lines either way
+        let field = IsoField::new_from((2, 2), vec![1.0, 0.0, 0.0, 1.0]);
+        for saddle in [SaddleResolution::Join, SaddleResolution::Split]{
+            let lines = field.isolines(&IsoMeshSettings{ saddle, iso_level: 0.5, ..default() });
+            assert_eq!(lines.len(), 2, "{saddle:?} {lines:?}");
+            assert!(lines.iter().all(|line| !line.closed && line.points.len() == 2));
+        }
+    }
+}
@@ -1,11 +1,55 @@
+// for the crate name, BirdBoxes, nothing else in the crate needs it
+#![allow(non_snake_case)]
 use bevy::prelude::*;
 use bevy::{
     render::{
         mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology
-    }, sprite::Mesh2dHandle, utils::HashMap,
-    log::info,
+    }, sprite::Mesh2dHandle, utils::HashSet,
+    log::info, ecs::{system::SystemParam, query::QueryData},
 };
 
+mod isoline;
+mod submesh;
+mod bands;
+mod materials;
+mod uv;
+mod color;
+mod extrude;
+mod field3d;
+mod validate;
+mod resize;
+mod brush;
+mod sdf;
+mod noise;
+mod caves;
+mod asset;
+mod image_loader;
+mod save;
+mod query;
+mod raycast;
+pub use isoline::*;
+pub use submesh::IsoSubMesh;
+pub use bands::*;
+pub use materials::*;
+pub use uv::IsoUvMode;
+use uv::UvGenerator;
+pub use color::{IsoGradient, IsoVertexColors};
+use color::vertex_colors;
+pub use extrude::*;
+pub use field3d::{IsoField3d, IsoField3dBundle, IsoSample3d, IsoSamples3d};
+use field3d::{add_mesh_3d, update_mesh_3d};
+pub use validate::{validate_mesh, IsoMeshReport};
+pub use resize::IsoAnchor;
+pub use brush::{IsoBlend, IsoBrush, IsoBrushShape, IsoFalloff};
+pub use sdf::IsoShape;
+pub use noise::{IsoFractal, IsoNoise, IsoNoiseKind};
+pub use caves::IsoCaves;
+pub use asset::IsoFieldAsset;
+use asset::sync_field_assets;
+pub use image_loader::{IsoImageChannel, IsoImageError, IsoImageLoader, IsoImageSettings};
+pub use save::{IsoFieldLoader, IsoSaveError, ISO_FIELD_MAX_SAMPLES, ISO_FIELD_VERSION};
+pub use raycast::{IsoRayHit, IsoRaycast};
+
 pub struct BirdBoxesPlugin;
 impl Plugin for BirdBoxesPlugin{
     fn build(&self, app: &mut App) {
@@ -13,12 +57,37 @@ impl Plugin for BirdBoxesPlugin{
             .init_resource::<ChunkSize>()
             .init_resource::<IsoLevel>()
             .init_resource::<IsoDistance>()
-            .add_systems(PreUpdate, (add_mesh, update_mesh).chain());
+            .init_resource::<IsoInvert>()
+            .init_resource::<IsoInterpolation>()
+            .init_resource::<SaddleResolution>()
+            .init_resource::<IsoGreedyMerge>()
+            .init_resource::<IsoUvMode>()
+            .init_resource::<IsoVertexColors>()
+            .register_type::<IsoField>()
+            .register_type::<ChunkSize>()
+            .register_type::<IsoLevel>()
+            .register_type::<IsoDistance>()
+            .register_type::<IsoInvert>()
+            .register_type::<IsoInterpolation>()
+            .register_type::<SaddleResolution>()
+            .register_type::<IsoGreedyMerge>()
+            .register_type::<IsoUvMode>()
+            .register_type::<IsoVertexColors>()
+            .add_systems(PreUpdate, (sync_field_assets, add_mesh, update_mesh, update_extrusions, add_mesh_3d, update_mesh_3d).chain());
+    }
+
+    // the AssetPlugin may be added after this plugin, it is only there once every plugin is built
+    fn finish(&self, app: &mut App) {
+        app
+            .init_asset::<IsoFieldAsset>()
+            .register_asset_loader(IsoImageLoader)
+            .register_asset_loader(IsoFieldLoader);
     }
 }
 
 ///The Size Of the IsoField
-#[derive(Resource, Debug)]
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource, Default)]
 pub struct ChunkSize(pub u32, pub u32);
 impl Default for ChunkSize{
     fn default() -> Self{
@@ -26,8 +95,10 @@ impl Default for ChunkSize{
     }
 }
 
-///The threshold when 
-#[derive(Resource, Debug)]
+///The threshold when a sample counts as inside the mesh
+///Insert it on an IsoField entity to override the global one
+#[derive(Resource, Component, Debug, Clone, Copy, Reflect)]
+#[reflect(Resource, Component, Default)]
 pub struct IsoLevel(pub f32);
 impl Default for IsoLevel{
     fn default() -> Self{
@@ -35,7 +106,10 @@ impl Default for IsoLevel{
     }
 }
 
-#[derive(Resource, Debug)]
+///The spacing between two samples of the IsoField
+///Insert it on an IsoField entity to override the global one
+#[derive(Resource, Component, Debug, Clone, Copy, Reflect)]
+#[reflect(Resource, Component, Default)]
 pub struct IsoDistance(pub f32);
 impl Default for IsoDistance{
     fn default() -> Self{
@@ -43,35 +117,192 @@ impl Default for IsoDistance{
     }
 }
 
+///Fill where the field is at or below the IsoLevel instead of above it, for fields
+///like distances or fog. Insert it on an IsoField entity to override the global one
+#[derive(Resource, Component, Debug, Default, Clone, Copy, Reflect)]
+#[reflect(Resource, Component, Default)]
+pub struct IsoInvert(pub bool);
+
+///Where the vertices on a cell edge get placed
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource, Default)]
+pub enum IsoInterpolation{
+    ///Always on the middle of the edge, gives the blocky look
+    #[default]
+    Midpoint,
+    ///Where the field crosses the iso level between the two corners
+    Linear,
+}
+
+///How the two ambiguous saddle cases get connected
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource, Default)]
+pub enum SaddleResolution{
+    ///The two solid corners are always joined through the middle of the cell
+    Join,
+    ///The two solid corners are always kept apart
+    Split,
+    ///Join when the bilinear field is solid at the saddle point (asymptotic decider)
+    #[default]
+    Decide,
+}
+
+///Merge fully solid cells into large quads, only the cells on the
+///contour get their own triangles. Quads bigger than one cell are fanned
+///from their center through every grid corner on their outline, so they
+///share vertices with the smaller cells around them.
+///Off by default: it only pays off on fields with large solid areas, where it
+///roughly drops the index count to a third, and is slower to build than plain
+///cells on fields with contours everywhere
+#[derive(Resource, Debug, Default, Clone, Copy, Reflect)]
+#[reflect(Resource, Default)]
+pub struct IsoGreedyMerge(pub bool);
+
+///Everything build_mesh needs to know, resolved from the plugin resources
+///and the per entity overrides
+#[derive(Debug, Clone)]
+pub struct IsoMeshSettings{
+    pub iso_distance: f32,
+    pub iso_level: f32,
+    pub invert: bool,
+    pub interpolation: IsoInterpolation,
+    pub saddle: SaddleResolution,
+    pub greedy_merge: bool,
+    pub uv_mode: IsoUvMode,
+    pub vertex_colors: Option<IsoGradient>,
+}
+
+impl Default for IsoMeshSettings{
+    fn default() -> Self{
+        Self{
+            iso_distance: IsoDistance::default().0,
+            iso_level: IsoLevel::default().0,
+            invert: IsoInvert::default().0,
+            interpolation: IsoInterpolation::default(),
+            saddle: SaddleResolution::default(),
+            greedy_merge: IsoGreedyMerge::default().0,
+            uv_mode: IsoUvMode::default(),
+            vertex_colors: None,
+        }
+    }
+}
+
+#[derive(SystemParam)]
+struct GlobalMeshSettings<'w, 's>{
+    iso_level: Res<'w, IsoLevel>,
+    iso_distance: Res<'w, IsoDistance>,
+    invert: Res<'w, IsoInvert>,
+    interpolation: Res<'w, IsoInterpolation>,
+    saddle: Res<'w, SaddleResolution>,
+    greedy_merge: Res<'w, IsoGreedyMerge>,
+    uv_mode: Res<'w, IsoUvMode>,
+    vertex_colors: Res<'w, IsoVertexColors>,
+    removed_levels: RemovedComponents<'w, 's, IsoLevel>,
+    removed_distances: RemovedComponents<'w, 's, IsoDistance>,
+    removed_inverts: RemovedComponents<'w, 's, IsoInvert>,
+}
+
+impl GlobalMeshSettings<'_, '_>{
+    fn changes(&mut self) -> MeshChanges{
+        MeshChanges{
+            globals: self.is_changed(),
+            // entities that lost an override fall back to the global value
+            removed: self.removed_levels.read()
+                .chain(self.removed_distances.read())
+                .chain(self.removed_inverts.read())
+                .collect(),
+        }
+    }
+
+    fn is_changed(&self) -> bool{
+        self.iso_level.is_changed()
+            || self.iso_distance.is_changed()
+            || self.invert.is_changed()
+            || self.interpolation.is_changed()
+            || self.saddle.is_changed()
+            || self.greedy_merge.is_changed()
+            || self.uv_mode.is_changed()
+            || self.vertex_colors.is_changed()
+    }
+
+    fn resolve(&self, overrides: &IsoOverridesItem) -> IsoMeshSettings{
+        IsoMeshSettings{
+            iso_distance: overrides.distance.as_deref().unwrap_or(&self.iso_distance).0,
+            iso_level: overrides.level.as_deref().unwrap_or(&self.iso_level).0,
+            invert: overrides.invert.as_deref().unwrap_or(&self.invert).0,
+            interpolation: *self.interpolation,
+            saddle: *self.saddle,
+            greedy_merge: self.greedy_merge.0,
+            uv_mode: *self.uv_mode,
+            vertex_colors: self.vertex_colors.0.clone(),
+        }
+    }
+}
+
+// The components that override the plugin resources on a single entity
+#[derive(QueryData)]
+struct IsoOverrides{
+    level: Option<Ref<'static, IsoLevel>>,
+    distance: Option<Ref<'static, IsoDistance>>,
+    invert: Option<Ref<'static, IsoInvert>>,
+}
+
+// What changed since a system last rebuilt its meshes
+struct MeshChanges{
+    globals: bool,
+    removed: HashSet<Entity>,
+}
+
+impl MeshChanges{
+    fn needs_rebuild(&self, entity: Entity, overrides: &IsoOverridesItem) -> bool{
+        self.globals
+            || self.removed.contains(&entity)
+            || overrides.level.as_ref().is_some_and(|level| level.is_changed())
+            || overrides.distance.as_ref().is_some_and(|distance| distance.is_changed())
+            || overrides.invert.as_ref().is_some_and(|invert| invert.is_changed())
+    }
+}
+
+///Marks an IsoField that is meshed by child entities (like IsoBands or IsoMaterials)
+///instead of getting a Mesh2dHandle of its own
+#[derive(Component, Debug, Default)]
+pub struct IsoMultiMesh;
+
 /////////
 
+#[allow(clippy::type_complexity)]
 fn add_mesh(
     mut commands: Commands,
-    iso_field_q: Query<(&IsoField, Entity), Without<Mesh2dHandle>>,
+    iso_field_q: Query<(&IsoField, Entity, IsoOverrides), (Without<Mesh2dHandle>, Without<IsoMultiMesh>, Without<IsoExtrusion>)>,
     mut meshes: ResMut<Assets<Mesh>>,
-    iso_level: Res<IsoLevel>,
-    iso_distance: Res<IsoDistance>,
+    settings: GlobalMeshSettings,
 ){
-    for (field, entity) in iso_field_q.iter(){
+    for (field, entity, overrides) in iso_field_q.iter(){
         info!("New Mesh");
+        let settings = settings.resolve(&overrides);
         let mesh_2d = Mesh2dHandle(meshes.add(field
                     .sample_all()
-                    .build_mesh(iso_distance.0, iso_level.0)));
+                    .build_mesh(&settings)));
         commands.entity(entity).insert(mesh_2d);
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn update_mesh(
-    mut iso_field_q: Query<(&IsoField, &mut Mesh2dHandle), Changed<IsoField>>,
+    mut iso_field_q: Query<(Entity, Ref<IsoField>, &mut Mesh2dHandle, IsoOverrides), Without<IsoMultiMesh>>,
     mut meshes: ResMut<Assets<Mesh>>,
-    iso_level: Res<IsoLevel>,
-    iso_distance: Res<IsoDistance>,
+    mut settings: GlobalMeshSettings,
 ){
-    for (iso_field, mut mesh_2d) in iso_field_q.iter_mut(){
+    let changes = settings.changes();
+    for (entity, iso_field, mut mesh_2d, overrides) in iso_field_q.iter_mut(){
+        if !(iso_field.is_changed() || changes.needs_rebuild(entity, &overrides)) {
+            continue;
+        }
         info!("Mesh Update");
+        let settings = settings.resolve(&overrides);
         let mesh = iso_field
                 .sample_all()
-                .build_mesh(iso_distance.0, iso_level.0);
+                .build_mesh(&settings);
         if let Some(stored_mesh) = meshes.get_mut(&mut mesh_2d.0){
             *stored_mesh = mesh;
         }
@@ -89,10 +320,13 @@ pub struct BirdBoxeBundle<M: Asset>{
     pub view_visibility: ViewVisibility,
 }
 
-#[derive(Component, Default)]
+#[derive(Component, Default, Clone, Reflect)]
+#[reflect(Component, Default)]
 pub struct IsoField{
     x_size: usize,
-    field: Vec<f32>
+    field: Vec<f32>,
+    // material id per sample, empty while everything is material 0
+    materials: Vec<u8>,
 }
 
 type Size = (usize, usize);
@@ -104,7 +338,8 @@ impl IsoField {
         let field = vec![0.0; x * y];
         Self{
             x_size: x,
-            field
+            field,
+            materials: Vec::new(),
         }
     }
 
@@ -116,13 +351,85 @@ impl IsoField {
         }
         Self{
             x_size: x,
-            field: vec
+            field: vec,
+            materials: Vec::new(),
+        }
+    }
+
+    ///Like new_from, but checks that `vec` holds exactly `x * y` samples in every build
+    pub fn try_new_from(size: impl Into<Size>, vec: Vec<f32>) -> Result<Self, IsoFieldError> {
+        let (x, y): Size = size.into();
+        if x == 0 || y == 0 {
+            return Err(IsoFieldError::Empty);
+        }
+        // a size too big to multiply can't match any vec
+        if x.checked_mul(y) != Some(vec.len()) {
+            return Err(IsoFieldError::SizeMismatch{ expected: x.saturating_mul(y), len: vec.len() });
         }
+        Ok(Self::new_from((x, y), vec))
     }
 }
 
+///Why a checked IsoField call failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsoFieldError{
+    ///The coordinates are outside of the `width` x `height` field
+    OutOfBounds{ x: usize, y: usize, width: usize, height: usize },
+    ///The sample vec doesn't match the size of the field
+    SizeMismatch{ expected: usize, len: usize },
+    ///The field has no samples
+    Empty,
+}
+
+impl std::fmt::Display for IsoFieldError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        match self {
+            Self::OutOfBounds{ x, y, width, height } => write!(f, "({x}, {y}) is outside of the {width}x{height} field"),
+            Self::SizeMismatch{ expected, len } => write!(f, "expected {expected} samples but got {len}"),
+            Self::Empty => write!(f, "the field has no samples"),
+        }
+    }
+}
+
+impl std::error::Error for IsoFieldError{}
+
 //getters and setters
 impl IsoField {
+    ///The number of samples in a row
+    pub fn width(&self) -> usize{
+        self.x_size
+    }
+    ///The number of rows
+    pub fn height(&self) -> usize{
+        self.field.len().checked_div(self.x_size).unwrap_or(0)
+    }
+    pub fn try_get(&self, x: usize, y: usize) -> Result<f32, IsoFieldError>{
+        self.check(x, y)?;
+        Ok(self.get(x, y))
+    }
+    pub fn try_set(&mut self, x: usize, y: usize, val: f32) -> Result<(), IsoFieldError>{
+        self.check(x, y)?;
+        self.set(x, y, val);
+        Ok(())
+    }
+    ///The cell with its bottom left corner at (x, y), so x and y stop one short of width and height
+    pub fn try_sample(&self, x: usize, y: usize) -> Result<IsoSample, IsoFieldError>{
+        self.check(x.saturating_add(1), y.saturating_add(1)).map_err(|error| match error {
+            IsoFieldError::OutOfBounds{ width, height, .. } => IsoFieldError::OutOfBounds{ x, y, width, height },
+            error => error,
+        })?;
+        Ok(self.sample(x, y))
+    }
+    fn check(&self, x: usize, y: usize) -> Result<(), IsoFieldError>{
+        let (width, height) = (self.width(), self.height());
+        if width == 0 || height == 0 {
+            return Err(IsoFieldError::Empty);
+        }
+        if x >= width || y >= height {
+            return Err(IsoFieldError::OutOfBounds{ x, y, width, height });
+        }
+        Ok(())
+    }
     pub fn get(&self, x: usize, y: usize) -> f32{
         self.field[self.index(x, y)]
     }
@@ -130,6 +437,38 @@ impl IsoField {
         let index = self.index(x, y);
         self.field[index] = val
     }
+    ///Every sample row by row from the bottom, sample (x, y) is at `y * width + x`
+    pub fn values(&self) -> &[f32]{
+        &self.field
+    }
+    pub fn values_mut(&mut self) -> &mut [f32]{
+        &mut self.field
+    }
+    ///The material of every sample in the same order as values, empty while everything is material 0
+    pub fn materials(&self) -> &[u8]{
+        &self.materials
+    }
+    ///Replaces every material at once, `materials` is either empty or one per sample
+    pub fn set_materials(&mut self, materials: Vec<u8>) -> Result<(), IsoFieldError>{
+        if !materials.is_empty() && materials.len() != self.field.len() {
+            return Err(IsoFieldError::SizeMismatch{ expected: self.field.len(), len: materials.len() });
+        }
+        self.materials = materials;
+        Ok(())
+    }
+    pub fn get_material(&self, x: usize, y: usize) -> u8{
+        self.materials.get(self.index(x, y)).copied().unwrap_or(0)
+    }
+    pub fn set_material(&mut self, x: usize, y: usize, material: u8){
+        let index = self.index(x, y);
+        if self.materials.is_empty() {
+            if material == 0 {
+                return;
+            }
+            self.materials = vec![0; self.field.len()];
+        }
+        self.materials[index] = material
+    }
     pub fn sample(&self, x: usize, y: usize) -> IsoSample{
         let sample = [
                     self.get(x, y ), // bottom left
@@ -141,217 +480,580 @@ impl IsoField {
     }
     pub fn sample_all(&self) -> IsoSamples {
         let mut samples = Vec::new();
-        let y_size = self.field.len() / self.x_size;
-        for y in 0..(y_size - 1){
-            for x in 0..(self.x_size - 1){
+        let y_size = self.height();
+        for y in 0..y_size.saturating_sub(1){
+            for x in 0..self.x_size.saturating_sub(1){
                 samples.push((self.sample(x, y), x, y));
             }
         }
-        IsoSamples(samples)
+        IsoSamples{
+            samples,
+            x_size: self.x_size,
+            y_size,
+        }
     }
     fn index(&self, x: usize, y: usize) -> usize{
-        y * self.x_size as usize + x
+        y * self.x_size + x
     }
 }
 
 
-pub struct IsoSamples(Vec<(IsoSample, usize, usize)>);
+pub struct IsoSamples{
+    // sorted by row, then column
+    samples: Vec<(IsoSample, usize, usize)>,
+    x_size: usize,
+    y_size: usize,
+}
 impl Iterator for IsoSamples {
     type Item = (IsoSample, usize, usize);
     fn next(&mut self) -> Option<Self::Item>{
-        self.0.pop()
+        self.samples.pop()
     }
 }
 
 impl IsoSamples {
-    fn build_mesh(self, iso_distance: f32, iso_level: f32) -> Mesh {
-        info!("Building Mesh[\n   iso_distance:{iso_distance}\n   iso_level:{iso_level}\n]");
-        let mut used_indices = HashMap::<HashAbleVec2, usize>::new();
-        let mut vertexes = Vec::<Vec3>::new();
-        let mut indices = Vec::<u32>::new();
-        let mut normals = Vec::<Vec3>::new();
-        //let mut face_count = Vec::<usize>::new();
-        let mut uvs = Vec::<Vec2>::new();
-        'a:for (sample, x, y) in self{
-            for tri in sample.to_tri_list(0.5){
-                for tri_index in tri{
-                    if let Some(vertex) = tri_index_to_vertex(tri_index){
-                        let vertex = {
-                            let x_off = x as f32 * iso_distance;
-                            let y_off = y as f32 * iso_distance;
-                            Vec2::new(x_off, y_off) + vertex * iso_distance
-                        };
-                        let h_vertex = HashAbleVec2::from(vertex.clone());
-                        if let Some(indice) = used_indices.get(&h_vertex){
-                            indices.push(*indice as u32);
-                        } else {
-                            // add vertex
-                            let indice = vertexes.len();
-                            used_indices.insert(h_vertex, indice);
-                            vertexes.push(vertex.extend(0.0));
-                            indices.push(indice as u32);
-                            normals.push(Vec3::new(0.0, 0.0, 1.0));
-                            uvs.push(Vec2::new(0.0, 0.0));
-                            //face_count.push(0);
-                        }
-                    } else {
-                        continue 'a;
+    pub fn build_mesh(self, settings: &IsoMeshSettings) -> Mesh {
+        info!("Building Mesh {settings:?}");
+        let uvs = UvGenerator::new(&self, settings);
+        self.mesh_buffers(settings).build(&uvs, settings.vertex_colors.as_ref())
+    }
+
+    // The flat triangles of build_mesh, before they are put in a Mesh
+    pub(crate) fn mesh_buffers(self, settings: &IsoMeshSettings) -> MeshBuffers {
+        let IsoMeshSettings{ iso_distance, iso_level, invert, interpolation, saddle, greedy_merge, .. } = *settings;
+        let mut buffers = MeshBuffers::default();
+        let mut cache = RowCache::new(self.x_size);
+
+        let mut samples = self.samples;
+        // merged quads would flatten the vertex colors
+        let quads = if greedy_merge && settings.vertex_colors.is_none() {
+            take_solid_rects(&mut samples, iso_level, invert)
+        } else {
+            Vec::new()
+        };
+        let mut quads = quads.into_iter().peekable();
+        let mut open_quads = Vec::<OpenQuad>::new();
+        let mut samples = samples.into_iter().peekable();
+
+        for y in 0..self.y_size.saturating_sub(1){
+            // quads are only made without vertex colors, so their corners need no value
+            let corner = |x: usize, y: usize| (Vec2::new(x as f32, y as f32) * iso_distance, f32::NAN);
+            while let Some((min, max)) = quads.next_if(|(min, _)| min.1 == y){
+                let bottom = (min.0..=max.0)
+                    .map(|x| buffers.vertex(&mut cache.corners[0][x], || corner(x, y)))
+                    .collect();
+                open_quads.push(OpenQuad{ min, max, bottom, right: Vec::new(), left: Vec::new() });
+            }
+            open_quads.retain_mut(|quad| {
+                let (min, max) = (quad.min, quad.max);
+                if min.1 < y {
+                    quad.right.push(buffers.vertex(&mut cache.corners[0][max.0], || corner(max.0, y)));
+                    quad.left.push(buffers.vertex(&mut cache.corners[0][min.0], || corner(min.0, y)));
+                }
+                if max.1 != y + 1 {
+                    return true;
+                }
+                let top: Vec<u32> = (min.0..=max.0)
+                    .map(|x| buffers.vertex(&mut cache.corners[1][x], || corner(x, y + 1)))
+                    .collect();
+                // every grid corner on the outline is a vertex, so the cells and quads
+                // next to it meet the quad at its vertices instead of inside its edges
+                let outline: Vec<u32> = quad.bottom.iter()
+                    .chain(&quad.right)
+                    .chain(top.iter().rev())
+                    .chain(quad.left.iter().rev())
+                    .copied()
+                    .collect();
+                if let [bottom_left, bottom_right, top_right, top_left] = outline[..] {
+                    buffers.indices.extend([bottom_left, top_right, top_left, bottom_left, bottom_right, top_right]);
+                } else {
+                    let center = Vec2::new((min.0 + max.0) as f32, (min.1 + max.1) as f32) * 0.5;
+                    // only this quad uses its center
+                    let mut center_slot = u32::MAX;
+                    let center = buffers.vertex(&mut center_slot, || (center * iso_distance, f32::NAN));
+                    for (i, start) in outline.iter().enumerate(){
+                        buffers.indices.extend([center, *start, outline[(i + 1) % outline.len()]]);
+                    }
+                }
+                false
+            });
+
+            while let Some((sample, x, _)) = samples.next_if(|(_, _, sample_y)| *sample_y == y){
+                let origin = Vec2::new(x as f32, y as f32);
+                for tri in sample.to_tri_list(iso_level, invert, saddle){
+                    if tri[0] == -1 {
+                        break;
+                    }
+                    for tri_index in tri{
+                        let indice = buffers.vertex(cache.slot(tri_index, x), || {
+                            let vertex = tri_index_to_vertex(tri_index, &sample, iso_level, interpolation).unwrap();
+                            ((origin + vertex) * iso_distance, sample.value_at(tri_index, iso_level))
+                        });
+                        buffers.indices.push(indice);
                     }
                 }
             }
+            cache.next_row();
+        }
+        buffers
+    }
+}
+
+// A greedy quad that is still being meshed, with the vertices along its outline so far
+struct OpenQuad{
+    min: (usize, usize),
+    max: (usize, usize),
+    // left to right, with both corners
+    bottom: Vec<u32>,
+    // bottom to top, without the corners
+    right: Vec<u32>,
+    left: Vec<u32>,
+}
+
+#[derive(Default)]
+pub(crate) struct MeshBuffers{
+    pub(crate) vertexes: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    // field value at each vertex
+    pub(crate) values: Vec<f32>,
+    pub(crate) indices: Vec<u32>,
+}
+
+impl MeshBuffers{
+    // the index of the vertex in the slot, adding it the first time the slot is used
+    fn vertex(&mut self, slot: &mut u32, vertex: impl FnOnce() -> (Vec2, f32)) -> u32{
+        if *slot == u32::MAX {
+            let (position, value) = vertex();
+            *slot = self.vertexes.len() as u32;
+            self.vertexes.push(position.extend(0.0));
+            self.values.push(value);
+            self.normals.push(Vec3::Z);
         }
+        *slot
+    }
 
-        Mesh::new(
+    fn build(self, uvs: &UvGenerator, gradient: Option<&IsoGradient>) -> Mesh{
+        let uvs: Vec<Vec2> = self.vertexes.iter().map(|vertex| uvs.uv(vertex.truncate())).collect();
+        let mut mesh = Mesh::new(
             PrimitiveTopology::TriangleList,
             RenderAssetUsages::RENDER_WORLD,
         )
-            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vertexes)
-            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.vertexes)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
             .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
-            .with_inserted_indices(Indices::U32(indices))
+            .with_inserted_indices(Indices::U32(self.indices));
+        if let Some(gradient) = gradient {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vertex_colors(&self.values, gradient));
+        }
+        mesh
+    }
+}
+
+// Vertex indices for the row of cells being meshed, keyed by the grid corner
+// or sample edge a vertex comes from so neighbouring cells weld exactly
+struct RowCache{
+    // corners along the bottom and the top of the row
+    corners: [Vec<u32>; 2],
+    // horizontal edges along the bottom and the top of the row
+    horizontal: [Vec<u32>; 2],
+    // vertical edges inside the row
+    vertical: Vec<u32>,
+}
+
+impl RowCache{
+    fn new(x_size: usize) -> Self{
+        Self{
+            corners: [vec![u32::MAX; x_size], vec![u32::MAX; x_size]],
+            horizontal: [vec![u32::MAX; x_size], vec![u32::MAX; x_size]],
+            vertical: vec![u32::MAX; x_size],
+        }
+    }
+
+    // the slot of a CASE_TABLE vertex in the cell at column x
+    fn slot(&mut self, index: i8, x: usize) -> &mut u32{
+        match index {
+            0 => &mut self.corners[0][x],
+            1 => &mut self.vertical[x],
+            2 => &mut self.corners[1][x],
+            3 => &mut self.horizontal[1][x],
+            4 => &mut self.corners[1][x + 1],
+            5 => &mut self.vertical[x + 1],
+            6 => &mut self.corners[0][x + 1],
+            7 => &mut self.horizontal[0][x],
+            _ => unreachable!()
+        }
+    }
+
+    // the top of this row becomes the bottom of the next one
+    fn next_row(&mut self){
+        self.corners.swap(0, 1);
+        self.corners[1].fill(u32::MAX);
+        self.horizontal.swap(0, 1);
+        self.horizontal[1].fill(u32::MAX);
+        self.vertical.fill(u32::MAX);
+    }
+}
+
+// Pulls the fully solid cells out of the samples and greedily merges them
+// into rectangles of grid corners (min, max), sorted by their bottom row
+fn take_solid_rects(samples: &mut Vec<(IsoSample, usize, usize)>, iso_level: f32, invert: bool) -> Vec<((usize, usize), (usize, usize))>{
+    let width = samples.iter().map(|(_, x, _)| x + 1).max().unwrap_or(0);
+    let height = samples.iter().map(|(_, _, y)| y + 1).max().unwrap_or(0);
+    let mut solid = vec![false; width * height];
+    samples.retain(|(sample, x, y)| {
+        let full = sample.to_case(iso_level, invert) == 15;
+        solid[y * width + x] = full;
+        !full
+    });
+
+    let mut rects = Vec::new();
+    for y in 0..height{
+        let mut x = 0;
+        while x < width {
+            if !solid[y * width + x] {
+                x += 1;
+                continue;
+            }
+            let mut w = 1;
+            while x + w < width && solid[y * width + x + w] {
+                w += 1;
+            }
+            let mut h = 1;
+            while y + h < height && (x..x + w).all(|x| solid[(y + h) * width + x]) {
+                h += 1;
+            }
+            for row in y..y + h{
+                solid[row * width + x..row * width + x + w].fill(false);
+            }
+            rects.push(((x, y), (x + w, y + h)));
+            x += w;
+        }
     }
+    rects
 }
 
 pub struct IsoSample([f32; 4]);
 impl IsoSample{
-    pub fn to_case(self, iso_level: f32) -> u8{
+    ///The corners that are solid, as CASE_TABLE bits. With `invert` a corner is
+    ///solid at or below the iso level instead of above it
+    pub fn to_case(&self, iso_level: f32, invert: bool) -> u8{
         const MASK: [u8; 4] = [ 1, 2, 4, 8];
         let mut out = 0;
         for (i, f) in self.0.iter().enumerate(){
-            if *f > iso_level {
-                out = out | MASK[i];
+            if is_solid(*f, iso_level, invert) {
+                out |= MASK[i];
             }
         }
         out
     }
 
-    pub fn to_tri_list(self, iso_level: f32) -> [[i8; 3]; 4]{
-        let case = self.to_case(iso_level) as usize;
-        CASE_TABLE[case]
+    pub fn to_tri_list(&self, iso_level: f32, invert: bool, saddle: SaddleResolution) -> [[i8; 3]; 4]{
+        let case = self.to_case(iso_level, invert) as usize;
+        match case {
+            5 if !self.saddle_joined(iso_level, invert, saddle) => SPLIT_SADDLE_TABLE[0],
+            10 if !self.saddle_joined(iso_level, invert, saddle) => SPLIT_SADDLE_TABLE[1],
+            _ => CASE_TABLE[case],
+        }
+    }
+
+    ///If the two solid corners of a saddle case should be connected
+    pub fn saddle_joined(&self, iso_level: f32, invert: bool, saddle: SaddleResolution) -> bool{
+        match saddle {
+            SaddleResolution::Join => true,
+            SaddleResolution::Split => false,
+            SaddleResolution::Decide => {
+                let [bl, tl, tr, br] = self.0;
+                let denominator = bl + tr - tl - br;
+                let center = if denominator == 0.0 {
+                    (bl + tl + tr + br) / 4.0
+                } else {
+                    // value of the bilinear interpolation at its saddle point
+                    (bl * tr - tl * br) / denominator
+                };
+                is_solid(center, iso_level, invert)
+            }
+        }
+    }
+
+    ///How far along the edge from corner `a` to corner `b` the iso level is crossed (0..1)
+    pub fn edge_crossing(&self, a: usize, b: usize, iso_level: f32, interpolation: IsoInterpolation) -> f32{
+        Self::crossing(self.0[a], self.0[b], iso_level, interpolation)
+    }
+
+    ///The field value at a vertex of the CASE_TABLE, edge vertices sit on the iso level
+    pub fn value_at(&self, index: i8, iso_level: f32) -> f32{
+        match index {
+            0 => self.0[0],
+            2 => self.0[1],
+            4 => self.0[2],
+            6 => self.0[3],
+            _ => iso_level,
+        }
+    }
+
+    ///How far from value `a` to value `b` the iso level is crossed (0..1), kept
+    ///a tiny bit away from both ends
+    pub fn crossing(a: f32, b: f32, iso_level: f32, interpolation: IsoInterpolation) -> f32{
+        match interpolation {
+            IsoInterpolation::Midpoint => 0.5,
+            IsoInterpolation::Linear => {
+                if a == b {
+                    return 0.5;
+                }
+                // a sample right on the level would put the crossings of both its edges
+                // on the corner, with a triangle without area between them
+                ((iso_level - a) / (b - a)).clamp(MIN_CROSSING, 1.0 - MIN_CROSSING)
+            }
+        }
     }
 }
 
-fn tri_index_to_vertex(index: i8) -> Option<Vec2>{
+// How close to a corner an edge crossing gets, in cells
+const MIN_CROSSING: f32 = 1e-3;
+
+// If a sample counts as inside the mesh. Inverting flips it exactly, a sample right on the
+// level is empty normally and solid when inverted
+pub(crate) fn is_solid(value: f32, iso_level: f32, invert: bool) -> bool{
+    if invert {
+        value <= iso_level
+    } else {
+        value > iso_level
+    }
+}
+
+fn tri_index_to_vertex(index: i8, sample: &IsoSample, iso_level: f32, interpolation: IsoInterpolation) -> Option<Vec2>{
+    let t = |a, b| sample.edge_crossing(a, b, iso_level, interpolation);
     Some(match index {
         -1 => {return None;},
         0 => Vec2::new(0.0, 0.0),
-        1 => Vec2::new(0.0, 0.5),
+        1 => Vec2::new(0.0, t(0, 1)),
         2 => Vec2::new(0.0, 1.0),
-        3 => Vec2::new(0.5, 1.0),
+        3 => Vec2::new(t(1, 2), 1.0),
         4 => Vec2::new(1.0, 1.0),
-        5 => Vec2::new(1.0, 0.5),
+        5 => Vec2::new(1.0, t(3, 2)),
         6 => Vec2::new(1.0, 0.0),
-        7 => Vec2::new(0.5, 0.0),
+        7 => Vec2::new(t(0, 3), 0.0),
         _ => unreachable!()
     })
 }
 
-#[derive(Hash, Eq, PartialEq)]
-struct HashAbleVec2{
-    x: (u32, i16, i8),
-    y: (u32, i16, i8),
-}
-
-impl From<Vec2> for HashAbleVec2{
-    fn from(val: Vec2) -> Self{
-        Self { x: integer_decode(val.x), y: integer_decode(val.y) }
-    }
-}
-
-fn integer_decode(val: f32) -> (u32, i16, i8) {
-    let bits: u32 = unsafe { std::mem::transmute(val) };
-    let sign: i8 = if bits >> 31 == 0 { 1 } else { -1 };
-    let mut exponent: i16 = ((bits >> 23) & 0xff) as i16;
-    let mantissa = if exponent == 0 {
-        (bits & 0x7fffff) << 1
-    } else {
-        (bits & 0x7fffff) | 0x800000
-    };
-
-    exponent -= 127 + 23;
-    (mantissa, exponent, sign)
-}
-
-
+// Indexed by the case returned from `IsoSample::to_case`.
+// Corner bits: 1 = bottom left, 2 = top left, 4 = top right, 8 = bottom right.
+// Every triangle is counter clockwise, so the mesh faces +Z
 const CASE_TABLE: [[[i8; 3]; 4]; 16] = [
-    // 1
+    // 0
     // [0][0] 0 0 0
     // [0][0] 0 0 0
     //        0 0 0
     [[-1, -1, -1], [-1, -1, -1], [-1, -1, -1], [-1, -1, -1]],
-    // 2
+    // 1
     // [0][0] 0 0 0
     // [1][0] \ 0 0
     //        1 \ 0
-    [[0, 1, 7], [-1, -1, -1], [-1, -1, -1], [-1, -1, -1]],
-    // 3
+    [[0, 7, 1], [-1, -1, -1], [-1, -1, -1], [-1, -1, -1]],
+    // 2
     // [1][0] 1 / 0
     // [0][0] / 0 0
     //        0 0 0
-    [[1, 2, 3], [-1, -1, -1], [-1, -1, -1], [-1, -1, -1]],
+    [[1, 3, 2], [-1, -1, -1], [-1, -1, -1], [-1, -1, -1]],
+    // 3
+    // [1][0] 1 | 0
+    // [1][0] 1 | 0
+    //        1 | 0
+    [[0, 3, 2], [0, 7, 3], [-1, -1, -1], [-1, -1, -1]],
     // 4
     // [0][1] 0 \ 1
     // [0][0] 0 0 \
     //        0 0 0
-    [[3, 4, 5], [-1, -1, -1], [-1, -1, -1], [-1, -1, -1]],
+    [[3, 5, 4], [-1, -1, -1], [-1, -1, -1], [-1, -1, -1]],
     // 5
+    // [0][1] 0 / 1
+    // [1][0] / 1 /
+    //        1 / 0
+    [[0, 7, 1], [1, 7, 3], [3, 7, 5], [3, 5, 4]],
+    // 6
+    // [1][1] 1 1 1
+    // [0][0] - - -
+    //        0 0 0
+    [[1, 4, 2], [1, 5, 4], [-1, -1, -1], [-1, -1, -1]],
+    // 7
+    // [1][1] 1 1 1
+    // [1][0] 1 1 /
+    //        1 / 0
+    [[0, 7, 2], [2, 7, 5], [2, 5, 4], [-1, -1, -1]],
+    // 8
     // [0][0] 0 0 0
     // [0][1] 0 0 /
     //        0 / 1
-    [[5, 6, 7], [-1, -1, -1], [-1, -1, -1], [-1, -1, -1]],
-    // 6
-    // [1][0] 1 | 0
-    // [1][0] 1 | 0
-    //        1 | 0
-    [[0, 2, 3], [0, 3, 7], [-1, -1, -1], [-1, -1, -1]],
-    // 7
+    [[5, 7, 6], [-1, -1, -1], [-1, -1, -1], [-1, -1, -1]],
+    // 9
     // [0][0] 0 0 0
     // [1][1] - - -
     //        1 1 1
-    [[0, 1, 5], [0, 5, 6], [-1, -1, -1], [-1, -1, -1]],
-    // 8
-    // [0][1] 0 / 1
-    // [1][0] / 1 /
-    //        1 / 0
-    [[0, 1, 7], [1, 3, 7], [3, 6, 7], [3, 4, 5]],
-    // 9
+    [[0, 5, 1], [0, 6, 5], [-1, -1, -1], [-1, -1, -1]],
+    // 10
     // [1][0] 1 \ 0
     // [0][1] \ 1 \
     //        0 \ 1
-    [[1, 2, 3], [1, 3, 7], [3, 5, 7], [5, 6, 7]],
-    // 10
-    // [1][1] 1 1 1
-    // [0][0] - - -
-    //        0 0 0
-    [[1, 2, 4], [1, 4, 5], [-1, -1, -1], [-1, -1, -1]],
+    [[1, 3, 2], [1, 7, 3], [3, 7, 5], [5, 7, 6]],
     // 11
+    // [1][0] 1 \ 0
+    // [1][1] 1 1 \
+    //        1 1 1
+    [[0, 3, 2], [0, 5, 3], [0, 6, 5], [-1, -1, -1]],
+    // 12
     // [0][1] 0 | 1
     // [0][1] 0 | 1
     //        0 | 1
-    [[0, 2,  3], [ 0, 3, 7], [-1, -1, -1], [-1, -1, -1]],
-    // 12
-    // [1][1] 1 1 1
-    // [1][0] 1 1 /
-    //        1 / 0
-    [[0, 2, 7], [2, 5, 7], [2, 4, 5], [-1, -1, -1]],
+    [[3, 6, 4], [3, 7, 6], [-1, -1, -1], [-1, -1, -1]],
     // 13
-    // [1][0] 1 \ 0
-    // [1][1] 1 1 \
-    //        1 1 1
-    [[0, 2, 3], [0, 3, 5], [0, 5, 6], [-1, -1, -1]],
-    // 14
     // [0][1] 0 / 1
     // [1][1] / 1 1
     //        1 1 1
-    [[1, 3, 6], [0, 1, 6], [3, 4, 6], [-1, -1, -1]],
-    // 15
+    [[1, 6, 3], [0, 6, 1], [3, 6, 4], [-1, -1, -1]],
+    // 14
     // [1][1] 1 1 1
     // [0][1] \ 1 1
     //        0 \ 1
-    [[1, 2, 4], [1, 4, 7], [4, 6, 7], [-1, -1, -1]],
-    // 16
+    [[1, 4, 2], [1, 7, 4], [4, 7, 6], [-1, -1, -1]],
+    // 15
     // [1][1] 1 1 1
     // [1][1] 1 1 1
     //        1 1 1
-    [[0, 2, 4], [0, 4, 6], [-1, -1, -1], [-1, -1, -1]],
+    [[0, 4, 2], [0, 6, 4], [-1, -1, -1], [-1, -1, -1]],
+];
+
+// Saddle cases 5 and 10 with the solid corners kept apart
+const SPLIT_SADDLE_TABLE: [[[i8; 3]; 4]; 2] = [
+    // 5
+    // [0][1] 0 \ 1
+    // [1][0] \ 0 \
+    //        1 \ 0
+    [[0, 7, 1], [3, 5, 4], [-1, -1, -1], [-1, -1, -1]],
+    // 10
+    // [1][0] 1 / 0
+    // [0][1] / 0 /
+    //        0 / 1
+    [[1, 3, 2], [5, 7, 6], [-1, -1, -1], [-1, -1, -1]],
 ];
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use bevy::scene::{ron, serde::SceneDeserializer, DynamicSceneBuilder};
+    use serde::de::DeserializeSeed;
+
+    #[test]
+    fn midpoints_by_default(){
+        // the pixel art levels rely on the blocky look, Linear is opt in
+        assert_eq!(IsoInterpolation::default(), IsoInterpolation::Midpoint);
+        assert_eq!(IsoMeshSettings::default().interpolation, IsoInterpolation::Midpoint);
+    }
+
+    #[test]
+    fn plugin_before_asset_plugin(){
+        // like the hello_world example, which adds BirdBoxesPlugin before DefaultPlugins
+        let mut app = App::new();
+        app.add_plugins((BirdBoxesPlugin, MinimalPlugins, AssetPlugin::default()));
+        // the render plugins add meshes in a full app
+        app.init_asset::<Mesh>();
+        app.finish();
+        app.cleanup();
+        app.update();
+        assert!(app.world().contains_resource::<Assets<IsoFieldAsset>>());
+    }
+
+    #[test]
+    fn remesh_on_setting_changes(){
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), BirdBoxesPlugin));
+        app.init_asset::<Mesh>();
+        app.finish();
+        app.cleanup();
+        // a ramp, so every level puts the contour somewhere else
+        let field = IsoField::new_from((5, 2), vec![0.0, 1.0, 2.0, 3.0, 4.0, 0.0, 1.0, 2.0, 3.0, 4.0]);
+        let entity = app.world_mut().spawn(field.clone()).id();
+
+        let positions = |app: &App| {
+            let handle = app.world().get::<Mesh2dHandle>(entity).expect("no mesh handle");
+            let mesh = app.world().resource::<Assets<Mesh>>().get(&handle.0).expect("no mesh");
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION).and_then(|positions| positions.as_float3()).unwrap().to_vec()
+        };
+        let expected = |iso_level: f32| {
+            let mesh = field.sample_all().build_mesh(&IsoMeshSettings{ iso_level, ..default() });
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION).and_then(|positions| positions.as_float3()).unwrap().to_vec()
+        };
+        assert_ne!(expected(1.5), expected(2.5));
+
+        app.update();
+        assert_eq!(positions(&app), expected(IsoLevel::default().0), "new mesh");
+
+        app.insert_resource(IsoLevel(2.5));
+        app.update();
+        assert_eq!(positions(&app), expected(2.5), "global level");
+
+        app.world_mut().entity_mut(entity).insert(IsoLevel(1.5));
+        app.update();
+        assert_eq!(positions(&app), expected(1.5), "override");
+
+        app.world_mut().entity_mut(entity).remove::<IsoLevel>();
+        app.update();
+        assert_eq!(positions(&app), expected(2.5), "override removed");
+    }
+
+    #[test]
+    fn checked_access(){
+        let mut field = IsoField::try_new_from((3, 2), vec![0.0, 0.25, -1.5, 1.0, 0.75, 0.5]).unwrap();
+        assert_eq!(field.try_get(2, 1), Ok(0.5));
+        assert_eq!(field.try_set(1, 1, 2.0), Ok(()));
+        assert_eq!(field.get(1, 1), 2.0);
+        assert_eq!(field.try_sample(1, 0).map(|sample| sample.0), Ok(field.sample(1, 0).0));
+
+        let out = |x, y| IsoFieldError::OutOfBounds{ x, y, width: 3, height: 2 };
+        assert_eq!(field.try_get(3, 0), Err(out(3, 0)));
+        assert_eq!(field.try_get(0, 2), Err(out(0, 2)));
+        assert_eq!(field.try_set(usize::MAX, 0, 1.0), Err(out(usize::MAX, 0)));
+        // a cell needs the samples to its right and above as well
+        assert_eq!(field.try_sample(2, 0).err(), Some(out(2, 0)));
+        assert_eq!(field.try_sample(0, 1).err(), Some(out(0, 1)));
+        assert_eq!(field.try_sample(usize::MAX, usize::MAX).err(), Some(out(usize::MAX, usize::MAX)));
+        assert_eq!(IsoField::new((0, 0)).try_get(0, 0), Err(IsoFieldError::Empty));
+
+        assert_eq!(IsoField::try_new_from((3, 2), vec![0.0; 5]).err(), Some(IsoFieldError::SizeMismatch{ expected: 6, len: 5 }));
+        assert_eq!(IsoField::try_new_from((2, 2), vec![0.0; 6]).err(), Some(IsoFieldError::SizeMismatch{ expected: 4, len: 6 }));
+        assert_eq!(IsoField::try_new_from((0, 2), vec![]).err(), Some(IsoFieldError::Empty));
+        // would wrap around to 0 without checking the multiplication
+        assert_eq!(
+            IsoField::try_new_from((usize::MAX / 2 + 1, 2), vec![]).err(),
+            Some(IsoFieldError::SizeMismatch{ expected: usize::MAX, len: 0 }),
+        );
+    }
+
+    #[test]
+    fn field_round_trips_through_a_scene(){
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), BirdBoxesPlugin));
+        let mut field = IsoField::new_from((3, 2), vec![0.0, 0.25, -1.5, 1.0, 0.75, 0.5]);
+        field.set_materials(vec![0, 1, 2, 2, 1, 0]).unwrap();
+        let entity = app.world_mut().spawn((field.clone(), IsoLevel(0.3), IsoInvert(true))).id();
+
+        let world = app.world();
+        let scene = DynamicSceneBuilder::from_world(world).extract_entity(entity).build();
+        let registry = world.resource::<AppTypeRegistry>().read();
+        let text = scene.serialize(&registry).unwrap();
+
+        let mut deserializer = ron::de::Deserializer::from_str(&text).unwrap();
+        let loaded = SceneDeserializer{ type_registry: &registry }.deserialize(&mut deserializer).unwrap();
+        let [loaded] = &loaded.entities[..] else { panic!("expected one entity in {text}") };
+        let component = |name: &str| loaded.components.iter()
+            .find(|component| component.get_represented_type_info().map(|info| info.type_path()) == Some(name))
+            .unwrap_or_else(|| panic!("no {name} in {text}"));
+
+        let loaded_field = IsoField::from_reflect(component(IsoField::type_path()).as_reflect()).unwrap();
+        assert_eq!(loaded_field.width(), field.width());
+        assert_eq!(loaded_field.values(), field.values());
+        assert_eq!(loaded_field.materials(), field.materials());
+        assert_eq!(IsoLevel::from_reflect(component(IsoLevel::type_path()).as_reflect()).unwrap().0, 0.3);
+        assert!(IsoInvert::from_reflect(component(IsoInvert::type_path()).as_reflect()).unwrap().0);
+    }
+}
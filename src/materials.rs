@@ -0,0 +1,312 @@
+use bevy::prelude::*;
+use bevy::{sprite::Mesh2dHandle, log::info};
+use std::marker::PhantomData;
+
+use crate::{is_solid, tri_index_to_vertex, GlobalMeshSettings, IsoField, IsoMeshSettings, IsoMultiMesh, IsoOverrides, UvGenerator};
+use crate::submesh::{sync_submeshes, IsoSubMesh, IsoSubMeshes, PolygonMeshBuilder};
+
+///Meshes IsoFields that have IsoMaterials<M>, add one per material type
+pub struct IsoMaterialsPlugin<M: Asset>(PhantomData<M>);
+impl<M: Asset> Default for IsoMaterialsPlugin<M>{
+    fn default() -> Self{
+        Self(PhantomData)
+    }
+}
+
+impl<M: Asset> Plugin for IsoMaterialsPlugin<M>{
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, update_materials::<M>);
+    }
+}
+
+///The material for every material id painted with `IsoField::set_material`,
+///every material is meshed into its own IsoSubMesh child entity
+#[derive(Component, Clone)]
+pub struct IsoMaterials<M: Asset>(pub Vec<Handle<M>>);
+
+impl<M: Asset> Default for IsoMaterials<M>{
+    fn default() -> Self{
+        Self(Vec::new())
+    }
+}
+
+#[derive(Bundle, Default)]
+pub struct IsoMaterialsBundle<M: Asset>{
+    pub iso_field: IsoField,
+    pub materials: IsoMaterials<M>,
+    pub multi_mesh: IsoMultiMesh,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub inherited_visibility: InheritedVisibility,
+    pub view_visibility: ViewVisibility,
+}
+
+#[allow(clippy::type_complexity)]
+fn update_materials<M: Asset>(
+    mut commands: Commands,
+    materials_q: Query<(Entity, Ref<IsoField>, Ref<IsoMaterials<M>>, IsoOverrides, Option<&IsoSubMeshes>)>,
+    mut sub_mesh_q: Query<(&mut Mesh2dHandle, &mut Handle<M>), With<IsoSubMesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut settings: GlobalMeshSettings,
+){
+    let changes = settings.changes();
+    for (entity, iso_field, materials, overrides, children) in materials_q.iter(){
+        let changed = children.is_none()
+            || iso_field.is_changed()
+            || materials.is_changed()
+            || changes.needs_rebuild(entity, &overrides);
+        if !changed {
+            continue;
+        }
+        info!("Material Mesh Update");
+        let settings = settings.resolve(&overrides);
+        let material_meshes = iso_field
+            .build_material_meshes(&settings)
+            .into_iter()
+            .enumerate()
+            .map(|(material, mesh)| (mesh, materials.0.get(material).cloned().unwrap_or_default()))
+            .collect();
+        sync_submeshes(&mut commands, entity, children, material_meshes, &mut sub_mesh_q, &mut meshes);
+    }
+}
+
+// The part of the cell owned by each corner, in IsoSample corner order
+const QUADRANTS: [(bool, bool); 4] = [
+    // (left, bottom)
+    (true, true),
+    (true, false),
+    (false, false),
+    (false, true),
+];
+
+impl IsoField{
+    ///Builds one mesh per material id, from 0 up to the highest id in the field.
+    ///Every cell is split into quarters owned by its corners, so two materials
+    ///meet halfway between their samples and share that border exactly
+    pub fn build_material_meshes(&self, settings: &IsoMeshSettings) -> Vec<Mesh>{
+        info!("Building Material Meshes {settings:?}");
+        let material_count = self.materials.iter().max().map_or(1, |max| *max as usize + 1);
+        let mut builders: Vec<PolygonMeshBuilder> = (0..material_count)
+            .map(|_| PolygonMeshBuilder::default())
+            .collect();
+
+        let samples = self.sample_all();
+        let uvs = UvGenerator::new(&samples, settings);
+        let (x_cells, y_cells) = (samples.x_size.saturating_sub(1), samples.y_size.saturating_sub(1));
+        let split = |x: usize, y: usize| {
+            let owners = self.quadrant_materials(x, y, settings);
+            owners.iter().any(|owner| *owner != owners[0])
+        };
+        for (sample, x, y) in samples{
+            let origin = Vec2::new(x as f32, y as f32);
+            let owners = self.quadrant_materials(x, y, settings);
+            // left, top, right, bottom
+            let split_neighbours = [
+                x > 0 && split(x - 1, y),
+                y + 1 < y_cells && split(x, y + 1),
+                x + 1 < x_cells && split(x + 1, y),
+                y > 0 && split(x, y - 1),
+            ];
+            for tri in sample.to_tri_list(settings.iso_level, settings.invert, settings.saddle){
+                if tri[0] == -1 {
+                    break;
+                }
+                let polygon: Vec<(Vec2, f32)> = tri
+                    .iter()
+                    .map(|index| {
+                        let vertex = tri_index_to_vertex(*index, &sample, settings.iso_level, settings.interpolation).unwrap();
+                        (vertex, sample.value_at(*index, settings.iso_level))
+                    })
+                    .collect();
+
+                if owners.iter().all(|owner| *owner == owners[0]){
+                    let polygon = side_midpoints(&polygon, split_neighbours);
+                    builders[owners[0] as usize].add_polygon(&offset(&polygon, origin), settings.iso_distance);
+                    continue;
+                }
+                for (owner, (left, bottom)) in owners.iter().zip(QUADRANTS){
+                    let quadrant = clip_half(&polygon, true, left);
+                    let quadrant = clip_half(&quadrant, false, bottom);
+                    builders[*owner as usize].add_polygon(&offset(&quadrant, origin), settings.iso_distance);
+                }
+            }
+        }
+
+        builders.into_iter().map(|builder| builder.build(&uvs, settings.vertex_colors.as_ref())).collect()
+    }
+
+    // Empty corners hand their quarter to a solid neighbour, across the
+    // horizontal edge first, then the vertical one, then the diagonal
+    fn quadrant_materials(&self, x: usize, y: usize, settings: &IsoMeshSettings) -> [u8; 4]{
+        let corners = [(x, y), (x, y + 1), (x + 1, y + 1), (x + 1, y)];
+        let solid = corners.map(|(x, y)| is_solid(self.get(x, y), settings.iso_level, settings.invert));
+        let material = corners.map(|(x, y)| self.get_material(x, y));
+        let mut owners = material;
+        for i in 0..4{
+            if solid[i] {
+                continue;
+            }
+            let horizontal = 3 - i;
+            let vertical = i ^ 1;
+            let diagonal = (i + 2) % 4;
+            if let Some(neighbour) = [horizontal, vertical, diagonal].into_iter().find(|n| solid[*n]){
+                owners[i] = material[neighbour];
+            }
+        }
+        owners
+    }
+}
+
+fn offset(polygon: &[(Vec2, f32)], origin: Vec2) -> Vec<(Vec2, f32)>{
+    polygon.iter().map(|(point, value)| (origin + *point, *value)).collect()
+}
+
+// Adds the middle of the chosen cell sides (left, top, right, bottom) where the polygon
+// runs along them, so it meets the quarters of a split neighbour without a T-junction
+fn side_midpoints(polygon: &[(Vec2, f32)], sides: [bool; 4]) -> Vec<(Vec2, f32)>{
+    if !sides.contains(&true) {
+        return polygon.to_vec();
+    }
+    let midpoints = [Vec2::new(0.0, 0.5), Vec2::new(0.5, 1.0), Vec2::new(1.0, 0.5), Vec2::new(0.5, 0.0)];
+    let mut out = Vec::with_capacity(polygon.len() + 4);
+    for (i, &(point, value)) in polygon.iter().enumerate(){
+        let (next_point, next_value) = polygon[(i + 1) % polygon.len()];
+        out.push((point, value));
+        for (midpoint, _) in midpoints.iter().zip(sides).filter(|(_, side)| *side){
+            let along = next_point - point;
+            let t = (*midpoint - point).dot(along) / along.length_squared().max(f32::EPSILON);
+            let on_side = (midpoint.x == 0.5 && point.y == midpoint.y && next_point.y == midpoint.y)
+                || (midpoint.y == 0.5 && point.x == midpoint.x && next_point.x == midpoint.x);
+            if on_side && t > 0.0 && t < 1.0 {
+                out.push((*midpoint, value + (next_value - value) * t));
+            }
+        }
+    }
+    out
+}
+
+// Keeps the part of the polygon on one side of the middle of the cell
+fn clip_half(polygon: &[(Vec2, f32)], vertical_line: bool, low_side: bool) -> Vec<(Vec2, f32)>{
+    let axis = |point: Vec2| if vertical_line { point.x } else { point.y };
+    let inside = |point: Vec2| (axis(point) <= 0.5) == low_side || axis(point) == 0.5;
+    let mut out = Vec::with_capacity(polygon.len() + 2);
+    for (i, &(point, value)) in polygon.iter().enumerate(){
+        let (next_point, next_value) = polygon[(i + 1) % polygon.len()];
+        if inside(point) {
+            out.push((point, value));
+        }
+        if inside(point) != inside(next_point) {
+            // interpolate from the same end on both sides of a shared edge so the points weld
+            let ((a, a_value), (b, b_value)) = if (point.x, point.y) < (next_point.x, next_point.y) {
+                ((point, value), (next_point, next_value))
+            } else {
+                ((next_point, next_value), (point, value))
+            };
+            let t = (0.5 - axis(a)) / (axis(b) - axis(a));
+            let mut crossing = a + (b - a) * t;
+            if vertical_line {
+                crossing.x = 0.5;
+            } else {
+                crossing.y = 0.5;
+            }
+            out.push((crossing, a_value + (b_value - a_value) * t));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use bevy::render::{
+        mesh::{Indices, VertexAttributeValues}, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology
+    };
+    use bevy::utils::HashMap;
+    use crate::noise::hash;
+    use crate::{validate_mesh, SaddleResolution};
+
+    fn positions(mesh: &Mesh) -> Vec<[f32; 3]>{
+        match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(positions)) => positions.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn indices(mesh: &Mesh) -> Vec<u32>{
+        match mesh.indices() {
+            Some(Indices::U32(indices)) => indices.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    // all the submeshes in one mesh, so validate_mesh sees the borders between them
+    fn combined(meshes: &[Mesh]) -> Mesh{
+        let (mut all_positions, mut all_indices) = (Vec::new(), Vec::new());
+        for mesh in meshes{
+            let offset = all_positions.len() as u32;
+            all_indices.extend(indices(mesh).iter().map(|index| index + offset));
+            all_positions.extend(positions(mesh));
+        }
+        let normals = vec![[0.0, 0.0, 1.0]; all_positions.len()];
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, all_positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_indices(Indices::U32(all_indices))
+    }
+
+    // the edges only one triangle uses, by position
+    fn open_edges(mesh: &Mesh) -> Vec<[Vec2; 2]>{
+        let positions = positions(mesh);
+        let key = |index: u32| positions[index as usize].map(f32::to_bits);
+        let mut edges = HashMap::<_, [Vec2; 2]>::new();
+        for tri in indices(mesh).chunks_exact(3){
+            for (from, to) in [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])]{
+                let points = [from, to].map(|index| Vec3::from(positions[index as usize]).truncate());
+                edges.insert((key(from), key(to)), points);
+            }
+        }
+        edges.iter()
+            .filter(|((from, to), _)| !edges.contains_key(&(*to, *from)))
+            .map(|(_, points)| *points)
+            .collect()
+    }
+
+    fn random_materials(field: &mut IsoField, seed: u32, count: u32){
+        let len = field.values().len() as i32;
+        field.set_materials((0..len).map(|i| (hash(i, 1, seed) % count) as u8).collect()).unwrap();
+    }
+
+    #[test]
+    fn borders_weld(){
+        // a solid field is only open along its outline when the materials weld everywhere
+        for seed in 0..50{
+            let mut field = IsoField::new_from((8, 8), vec![1.0; 64]);
+            random_materials(&mut field, seed, 2);
+            let mesh = combined(&field.build_material_meshes(&IsoMeshSettings{ iso_level: 0.5, ..default() }));
+            let report = validate_mesh(&mesh);
+            assert!(report.is_valid(), "seed {seed}: {report:?}");
+            for [from, to] in open_edges(&mesh){
+                let on_outline = (from.x == to.x && (from.x == 0.0 || from.x == 7.0))
+                    || (from.y == to.y && (from.y == 0.0 || from.y == 7.0));
+                assert!(on_outline, "seed {seed}: open edge {from} {to} inside the field");
+            }
+        }
+    }
+
+    #[test]
+    fn random_fields_are_valid(){
+        for seed in 0..100{
+            let values = (0..64).map(|i| [0.0, 0.5, 1.0, 1.0][hash(i, 0, seed) as usize % 4]).collect();
+            let mut field = IsoField::new_from((8, 8), values);
+            random_materials(&mut field, seed, 3);
+            for saddle in [SaddleResolution::Join, SaddleResolution::Split, SaddleResolution::Decide]{
+                for invert in [false, true]{
+                    let settings = IsoMeshSettings{ saddle, invert, iso_level: 0.5, iso_distance: 0.5, ..default() };
+                    let report = validate_mesh(&combined(&field.build_material_meshes(&settings)));
+                    assert!(report.is_valid(), "seed {seed} {saddle:?} invert {invert}: {report:?}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use bevy::prelude::*;
+
+use crate::IsoField;
+
+///The base noise of an IsoNoise
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IsoNoiseKind{
+    ///Random values on the grid, eased in between
+    Value,
+    ///Random gradients on the grid
+    #[default]
+    Perlin,
+    ///Random gradients on a triangle grid, fewer grid artifacts than Perlin
+    Simplex,
+    ///The distance to the closest of one random point per grid cell,
+    ///low near the points and high in between
+    Worley,
+}
+
+///How octaves of the noise are layered, every octave has `lacunarity` times the
+///frequency and `gain` times the strength of the one before
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum IsoFractal{
+    ///A single octave
+    #[default]
+    Single,
+    ///Fractal brownian motion, the octaves are summed
+    Fbm{ octaves: u32, lacunarity: f32, gain: f32 },
+    ///Sharp ridges where the noise crosses its middle, good for canyons and tunnels
+    Ridged{ octaves: u32, lacunarity: f32, gain: f32 },
+}
+
+///Seeded coherent noise, the same seed always gives the same values.
+///Values are between 0 and 1, an IsoLevel of 0.5 cuts through the middle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IsoNoise{
+    pub kind: IsoNoiseKind,
+    pub fractal: IsoFractal,
+    pub seed: u32,
+    ///Grid cells of the first octave per world unit
+    pub frequency: f32,
+}
+
+impl IsoNoise{
+    pub fn new(kind: IsoNoiseKind, seed: u32) -> Self{
+        Self{ kind, fractal: IsoFractal::Single, seed, frequency: 0.1 }
+    }
+
+    pub fn with_fractal(mut self, fractal: IsoFractal) -> Self{
+        self.fractal = fractal;
+        self
+    }
+
+    pub fn with_frequency(mut self, frequency: f32) -> Self{
+        self.frequency = frequency;
+        self
+    }
+
+    ///The noise at a world position
+    pub fn sample(&self, point: Vec2) -> f32{
+        let point = point * self.frequency;
+        let (octaves, lacunarity, gain, ridged) = match self.fractal {
+            IsoFractal::Single => (1, 1.0, 1.0, false),
+            IsoFractal::Fbm{ octaves, lacunarity, gain } => (octaves.max(1), lacunarity, gain, false),
+            IsoFractal::Ridged{ octaves, lacunarity, gain } => (octaves.max(1), lacunarity, gain, true),
+        };
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        let mut strength = 1.0;
+        let mut frequency = 1.0;
+        for octave in 0..octaves{
+            let seed = self.seed.wrapping_add(octave.wrapping_mul(0x9e37_79b9));
+            let noise = self.base(point * frequency, seed);
+            sum += strength * if ridged { (1.0 - noise.abs()).powi(2) } else { noise * 0.5 + 0.5 };
+            // a negative gain flips every other octave, it can't cancel the total out
+            total += strength.abs();
+            strength *= gain;
+            frequency *= lacunarity;
+        }
+        (sum / total).clamp(0.0, 1.0)
+    }
+
+    // one octave, -1..1
+    fn base(&self, point: Vec2, seed: u32) -> f32{
+        match self.kind {
+            IsoNoiseKind::Value => value_noise(point, seed),
+            IsoNoiseKind::Perlin => perlin_noise(point, seed),
+            IsoNoiseKind::Simplex => simplex_noise(point, seed),
+            IsoNoiseKind::Worley => worley_noise(point, seed) * 2.0 - 1.0,
+        }
+    }
+}
+
+impl IsoField{
+    ///Sets every sample to the noise at its world position. `iso_distance` and `transform`
+    ///are the ones of the IsoField entity, so fields placed next to each other get matching
+    ///values along the shared border however they are moved, rotated or scaled
+    pub fn fill_noise(&mut self, noise: &IsoNoise, iso_distance: f32, transform: &GlobalTransform){
+        for y in 0..self.height(){
+            for x in 0..self.width(){
+                let position = Vec2::new(x as f32, y as f32) * iso_distance;
+                self.set(x, y, noise.sample(transform.transform_point(position.extend(0.0)).truncate()));
+            }
+        }
+    }
+}
+
+pub(crate) fn hash(x: i32, y: i32, seed: u32) -> u32{
+    let mut hash = seed;
+    for value in [x as u32, y as u32]{
+        hash ^= value.wrapping_mul(0x27d4_eb2d);
+        hash ^= hash >> 15;
+        hash = hash.wrapping_mul(0x2c1b_3c6d);
+        hash ^= hash >> 12;
+        hash = hash.wrapping_mul(0x297a_2d39);
+        hash ^= hash >> 15;
+    }
+    hash
+}
+
+// 0..1 from the top bits of the hash
+pub(crate) fn unit(hash: u32) -> f32{
+    (hash >> 8) as f32 / (1 << 24) as f32
+}
+
+fn gradient(hash: u32) -> Vec2{
+    let angle = unit(hash) * std::f32::consts::TAU;
+    Vec2::new(angle.cos(), angle.sin())
+}
+
+fn fade(t: Vec2) -> Vec2{
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn value_noise(point: Vec2, seed: u32) -> f32{
+    let cell = point.floor();
+    let (x, y) = (cell.x as i32, cell.y as i32);
+    let t = fade(point - cell);
+    let corner = |dx: i32, dy: i32| unit(hash(x + dx, y + dy, seed)) * 2.0 - 1.0;
+    let bottom = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * t.x;
+    let top = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * t.x;
+    bottom + (top - bottom) * t.y
+}
+
+fn perlin_noise(point: Vec2, seed: u32) -> f32{
+    let cell = point.floor();
+    let (x, y) = (cell.x as i32, cell.y as i32);
+    let local = point - cell;
+    let t = fade(local);
+    let corner = |dx: i32, dy: i32| gradient(hash(x + dx, y + dy, seed)).dot(local - Vec2::new(dx as f32, dy as f32));
+    let bottom = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * t.x;
+    let top = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * t.x;
+    // unit gradients reach at most sqrt(1/2)
+    ((bottom + (top - bottom) * t.y) * std::f32::consts::SQRT_2).clamp(-1.0, 1.0)
+}
+
+fn simplex_noise(point: Vec2, seed: u32) -> f32{
+    const SKEW: f32 = 0.366_025_42; // (sqrt(3) - 1) / 2
+    const UNSKEW: f32 = 0.211_324_87; // (3 - sqrt(3)) / 6
+    let cell = (point + Vec2::splat((point.x + point.y) * SKEW)).floor();
+    let origin = cell - Vec2::splat((cell.x + cell.y) * UNSKEW);
+    let local = point - origin;
+    let middle = if local.x > local.y { Vec2::X } else { Vec2::Y };
+    let corners = [Vec2::ZERO, middle, Vec2::ONE];
+    let mut sum = 0.0;
+    for corner in corners{
+        let offset = local - corner + Vec2::splat((corner.x + corner.y) * UNSKEW);
+        let falloff = 0.5 - offset.length_squared();
+        if falloff > 0.0 {
+            let corner_hash = hash(cell.x as i32 + corner.x as i32, cell.y as i32 + corner.y as i32, seed);
+            sum += falloff.powi(4) * gradient(corner_hash).dot(offset);
+        }
+    }
+    // brings the peaks of unit gradients up to about 1
+    (sum * 99.2).clamp(-1.0, 1.0)
+}
+
+fn worley_noise(point: Vec2, seed: u32) -> f32{
+    let cell = point.floor();
+    let mut closest = f32::MAX;
+    for dy in -1..=1{
+        for dx in -1..=1{
+            let (x, y) = (cell.x as i32 + dx, cell.y as i32 + dy);
+            let jitter = Vec2::new(unit(hash(x, y, seed)), unit(hash(x, y, seed ^ 0x5bd1_e995)));
+            let feature = Vec2::new(x as f32, y as f32) + jitter;
+            closest = closest.min(point.distance_squared(feature));
+        }
+    }
+    closest.sqrt().min(1.0)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn chunks_line_up(){
+        let noise = IsoNoise::new(IsoNoiseKind::Perlin, 7).with_fractal(IsoFractal::Fbm{ octaves: 3, lacunarity: 2.0, gain: 0.5 });
+        // two 5 × 5 chunks, rotated and scaled so each covers 12 × 12 world units, side by side
+        let rotation = Quat::from_rotation_z(0.7);
+        let chunk = |offset: f32| GlobalTransform::from(
+            Transform::from_translation(Vec3::Y + rotation * Vec3::X * offset)
+                .with_rotation(rotation)
+                .with_scale(Vec3::splat(3.0))
+        );
+        let (mut left_field, mut right_field) = (IsoField::new((5, 5)), IsoField::new((5, 5)));
+        left_field.fill_noise(&noise, 1.0, &chunk(0.0));
+        right_field.fill_noise(&noise, 1.0, &chunk(12.0));
+        for y in 0..5{
+            assert!((left_field.get(4, y) - right_field.get(0, y)).abs() < 1e-4, "row {y}");
+        }
+        assert_eq!(left_field.get(2, 2), noise.sample(chunk(0.0).transform_point(Vec3::new(2.0, 2.0, 0.0)).truncate()));
+    }
+
+    #[test]
+    fn negative_gain_stays_finite(){
+        for gain in [-1.0, -0.5, 0.0]{
+            let noise = IsoNoise::new(IsoNoiseKind::Value, 3).with_fractal(IsoFractal::Fbm{ octaves: 2, lacunarity: 1.0, gain });
+            for i in 0..50{
+                let value = noise.sample(Vec2::new(i as f32 * 1.3, i as f32 * 0.7));
+                assert!((0.0..=1.0).contains(&value), "gain {gain}: {value}");
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+
+use crate::{is_solid, IsoField, IsoMeshSettings};
+
+impl IsoField{
+    ///The bilinearly interpolated value at `point` in field coordinates, where sample (x, y) is at (x, y).
+    ///None outside of the field
+    pub fn sample_at(&self, point: Vec2) -> Option<f32>{
+        let (x0, y0, x1, y1, t) = self.cell_at(point)?;
+        let bottom = self.get(x0, y0).lerp(self.get(x1, y0), t.x);
+        let top = self.get(x0, y1).lerp(self.get(x1, y1), t.x);
+        Some(bottom.lerp(top, t.y))
+    }
+
+    ///The gradient of the bilinear field at `point` in field coordinates, the change in value per sample
+    pub fn gradient_at(&self, point: Vec2) -> Option<Vec2>{
+        let (x0, y0, x1, y1, t) = self.cell_at(point)?;
+        let (bl, tl, tr, br) = (self.get(x0, y0), self.get(x0, y1), self.get(x1, y1), self.get(x1, y0));
+        // a single row or column has no slope across it
+        let dx = if x1 > x0 { (br - bl).lerp(tr - tl, t.y) } else { 0.0 };
+        let dy = if y1 > y0 { (tl - bl).lerp(tr - br, t.x) } else { 0.0 };
+        Some(Vec2::new(dx, dy))
+    }
+
+    ///Like sample_at, but `position` is a world position.
+    ///`iso_distance` and `transform` are the ones of the IsoField entity
+    pub fn sample_world(&self, position: Vec2, iso_distance: f32, transform: &GlobalTransform) -> Option<f32>{
+        self.sample_at(to_field(position, iso_distance, transform))
+    }
+
+    ///The gradient at a world position, the change in value per world unit
+    pub fn gradient_world(&self, position: Vec2, iso_distance: f32, transform: &GlobalTransform) -> Option<Vec2>{
+        let gradient = self.gradient_at(to_field(position, iso_distance, transform))?;
+        Some(gradient_to_world(gradient, iso_distance, transform))
+    }
+
+    ///Whether the world position is inside the mesh, false outside of the field
+    pub fn is_solid(&self, position: Vec2, settings: &IsoMeshSettings, transform: &GlobalTransform) -> bool{
+        self.sample_world(position, settings.iso_distance, transform)
+            .is_some_and(|value| is_solid(value, settings.iso_level, settings.invert))
+    }
+
+    ///The direction out of the solid at a world position, the normal of the
+    ///surface when the position is on it. None where the field is flat
+    pub fn surface_normal(&self, position: Vec2, settings: &IsoMeshSettings, transform: &GlobalTransform) -> Option<Vec2>{
+        let gradient = self.gradient_world(position, settings.iso_distance, transform)?;
+        // values rise into the solid, or fall into it when inverted
+        let outward = if settings.invert { gradient } else { -gradient };
+        outward.try_normalize()
+    }
+
+    // the corners around a point and how far along the cell it is,
+    // the last row and column belong to the cell before them
+    fn cell_at(&self, point: Vec2) -> Option<(usize, usize, usize, usize, Vec2)>{
+        let (width, height) = (self.width(), self.height());
+        let max = Vec2::new(width as f32 - 1.0, height as f32 - 1.0);
+        if width == 0 || height == 0 || !(point.x >= 0.0 && point.y >= 0.0 && point.x <= max.x && point.y <= max.y) {
+            return None;
+        }
+        let x0 = (point.x as usize).min(width.saturating_sub(2));
+        let y0 = (point.y as usize).min(height.saturating_sub(2));
+        let x1 = (x0 + 1).min(width - 1);
+        let y1 = (y0 + 1).min(height - 1);
+        Some((x0, y0, x1, y1, point - Vec2::new(x0 as f32, y0 as f32)))
+    }
+}
+
+pub(crate) fn to_field(position: Vec2, iso_distance: f32, transform: &GlobalTransform) -> Vec2{
+    transform.affine().inverse().transform_point3(position.extend(0.0)).truncate() / iso_distance
+}
+
+// the chain rule through world -> field, which is the inverse transform scaled by 1 / iso_distance.
+// Also turns field normals into world normals
+pub(crate) fn gradient_to_world(gradient: Vec2, iso_distance: f32, transform: &GlobalTransform) -> Vec2{
+    let inverse = transform.affine().inverse().matrix3;
+    Vec2::new(gradient.dot(inverse.x_axis.truncate()), gradient.dot(inverse.y_axis.truncate())) / iso_distance
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    // value = 0.3 x - 0.2 y + 1 at sample (x, y), the bilinear field and its gradient are exact
+    const RAMP: Vec2 = Vec2::new(0.3, -0.2);
+
+    fn ramp() -> IsoField{
+        let mut field = IsoField::new((11, 9));
+        for y in 0..9{
+            for x in 0..11{
+                field.set(x, y, RAMP.dot(Vec2::new(x as f32, y as f32)) + 1.0);
+            }
+        }
+        field
+    }
+
+    const ANGLE: f32 = 0.5;
+    const SCALE: Vec2 = Vec2::new(2.0, 0.5);
+    const TRANSLATION: Vec2 = Vec2::new(3.0, -2.0);
+    const ISO_DISTANCE: f32 = 0.25;
+
+    fn transform() -> GlobalTransform{
+        GlobalTransform::from(
+            Transform::from_translation(TRANSLATION.extend(0.0))
+                .with_rotation(Quat::from_rotation_z(ANGLE))
+                .with_scale(SCALE.extend(1.0))
+        )
+    }
+
+    // the world position of field coordinates
+    fn to_world(point: Vec2) -> Vec2{
+        TRANSLATION + Vec2::from_angle(ANGLE).rotate(point * ISO_DISTANCE * SCALE)
+    }
+
+    // the ramp per world unit, the transpose of world -> field applied to it
+    fn world_gradient() -> Vec2{
+        let to_field = Mat2::from_diagonal(1.0 / (ISO_DISTANCE * SCALE)) * Mat2::from_angle(-ANGLE);
+        to_field.transpose() * RAMP
+    }
+
+    #[test]
+    fn sample_and_gradient(){
+        let (field, transform) = (ramp(), transform());
+        for point in [Vec2::new(3.3, 5.7), Vec2::ZERO, Vec2::new(10.0, 8.0), Vec2::new(9.99, 0.5), Vec2::new(4.0, 2.0)]{
+            let position = to_world(point);
+            let value = field.sample_world(position, ISO_DISTANCE, &transform).unwrap();
+            assert!((value - RAMP.dot(point) - 1.0).abs() < 1e-4, "{point}: {value}");
+            let gradient = field.gradient_world(position, ISO_DISTANCE, &transform).unwrap();
+            assert!(gradient.abs_diff_eq(world_gradient(), 1e-4), "{point}: {gradient} != {}", world_gradient());
+        }
+    }
+
+    #[test]
+    fn solid_and_normal(){
+        let (field, transform) = (ramp(), transform());
+        let mut settings = IsoMeshSettings{ iso_level: 1.0, iso_distance: ISO_DISTANCE, ..default() };
+        // the level runs through (0, 0) and (2, 3), the solid is on the right of it
+        let (inside, outside) = (to_world(Vec2::new(3.0, 3.0)), to_world(Vec2::new(1.0, 3.0)));
+        assert!(field.is_solid(inside, &settings, &transform));
+        assert!(!field.is_solid(outside, &settings, &transform));
+        let normal = field.surface_normal(to_world(Vec2::new(2.0, 3.0)), &settings, &transform).unwrap();
+        assert!(normal.abs_diff_eq(-world_gradient().normalize(), 1e-4), "{normal}");
+
+        settings.invert = true;
+        assert!(!field.is_solid(inside, &settings, &transform));
+        assert!(field.is_solid(outside, &settings, &transform));
+        let normal = field.surface_normal(to_world(Vec2::new(2.0, 3.0)), &settings, &transform).unwrap();
+        assert!(normal.abs_diff_eq(world_gradient().normalize(), 1e-4), "{normal}");
+    }
+
+    #[test]
+    fn outside_of_the_field(){
+        let (field, transform) = (ramp(), transform());
+        let settings = IsoMeshSettings{ iso_level: -10.0, iso_distance: ISO_DISTANCE, ..default() };
+        for point in [Vec2::new(-0.1, 3.0), Vec2::new(10.1, 3.0), Vec2::new(3.0, 8.5), Vec2::new(3.0, -1.0)]{
+            let position = to_world(point);
+            assert_eq!(field.sample_world(position, ISO_DISTANCE, &transform), None, "{point}");
+            assert_eq!(field.gradient_world(position, ISO_DISTANCE, &transform), None, "{point}");
+            assert!(!field.is_solid(position, &settings, &transform), "{point}");
+            assert_eq!(field.surface_normal(position, &settings, &transform), None, "{point}");
+        }
+        assert_eq!(IsoField::new((0, 0)).sample_world(Vec2::ZERO, 1.0, &GlobalTransform::IDENTITY), None);
+    }
+}
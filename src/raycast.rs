@@ -0,0 +1,310 @@
+use bevy::prelude::*;
+use bevy::ecs::{system::SystemParam, query::QueryData};
+
+use crate::query::{gradient_to_world, to_field};
+use crate::{is_solid, IsoDistance, IsoField, IsoInvert, IsoLevel, IsoMeshSettings};
+
+///Where a ray entered the solid part of an IsoField, in world space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IsoRayHit{
+    pub point: Vec2,
+    ///Points out of the solid. Rays that start inside the solid hit at distance 0
+    ///with the normal facing back along the ray
+    pub normal: Vec2,
+    ///From the ray origin to the point, in world units
+    pub distance: f32,
+}
+
+impl IsoField{
+    ///Casts a ray from the world position `origin` along `direction` and returns the first
+    ///point within `max_distance` where it enters the solid. The ray follows the bilinear
+    ///field inside each cell, which can be a little off the straight mesh edges.
+    ///`settings` and `transform` are the ones of the IsoField entity
+    pub fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: f32, settings: &IsoMeshSettings, transform: &GlobalTransform) -> Option<IsoRayHit>{
+        let direction = direction.try_normalize()?;
+        let (width, height) = (self.width(), self.height());
+        if width < 2 || height < 2 {
+            return None;
+        }
+        // t stays the world distance along the ray, the field position moves by `step` per unit
+        let start = to_field(origin, settings.iso_distance, transform);
+        let step = transform.affine().inverse().transform_vector3(direction.extend(0.0)).truncate() / settings.iso_distance;
+        let max = Vec2::new(width as f32 - 1.0, height as f32 - 1.0);
+
+        // clip the ray to the field
+        let (mut enter, mut exit) = (0.0f32, max_distance);
+        let mut enter_normal = None;
+        for axis in 0..2{
+            if step[axis] == 0.0 {
+                if start[axis] < 0.0 || start[axis] > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let near = -start[axis] / step[axis];
+            let far = (max[axis] - start[axis]) / step[axis];
+            let (near, far) = (near.min(far), near.max(far));
+            if near > enter {
+                enter = near;
+                let mut normal = Vec2::ZERO;
+                normal[axis] = -step[axis].signum();
+                enter_normal = Some(normal);
+            }
+            exit = exit.min(far);
+        }
+        if enter > exit {
+            return None;
+        }
+
+        let entry = start + step * enter;
+        if self.sample_at(entry.clamp(Vec2::ZERO, max)).is_some_and(|value| is_solid(value, settings.iso_level, settings.invert)) {
+            // the solid reaches the border of the field, or the ray starts inside it
+            let normal = match enter_normal {
+                Some(normal) => gradient_to_world(normal, settings.iso_distance, transform).normalize_or_zero(),
+                None => -direction,
+            };
+            return Some(IsoRayHit{ point: origin + direction * enter, normal, distance: enter });
+        }
+
+        // walk the cells along the ray
+        let mut cell = [
+            (entry.x.max(0.0) as usize).min(width - 2),
+            (entry.y.max(0.0) as usize).min(height - 2),
+        ];
+        let mut next = [f32::INFINITY; 2];
+        for axis in 0..2{
+            if step[axis] > 0.0 {
+                next[axis] = ((cell[axis] + 1) as f32 - start[axis]) / step[axis];
+            } else if step[axis] < 0.0 {
+                next[axis] = (cell[axis] as f32 - start[axis]) / step[axis];
+            }
+        }
+        let mut t = enter;
+        loop {
+            let cell_exit = next[0].min(next[1]).min(exit);
+            if let Some(crossing) = self.cell_crossing(cell, start + step * t, step, cell_exit - t, settings) {
+                let distance = t + crossing;
+                let point = origin + direction * distance;
+                let normal = self.surface_normal(point, settings, transform).unwrap_or(-direction);
+                return Some(IsoRayHit{ point, normal, distance });
+            }
+            if cell_exit >= exit {
+                return None;
+            }
+            let axis = if next[0] < next[1] { 0 } else { 1 };
+            let limit = if axis == 0 { width - 2 } else { height - 2 };
+            cell[axis] = if step[axis] > 0.0 {
+                (cell[axis] < limit).then_some(cell[axis] + 1)?
+            } else {
+                cell[axis].checked_sub(1)?
+            };
+            next[axis] += 1.0 / step[axis].abs();
+            t = cell_exit;
+        }
+    }
+
+    // How far along the ray, from `from` over `len`, it first enters the solid inside the cell.
+    // Along a line the bilinear field is a quadratic, so the crossings are its roots
+    fn cell_crossing(&self, [x, y]: [usize; 2], from: Vec2, step: Vec2, len: f32, settings: &IsoMeshSettings) -> Option<f32>{
+        let (bl, tl, tr, br) = (self.get(x, y), self.get(x, y + 1), self.get(x + 1, y + 1), self.get(x + 1, y));
+        let local = from - Vec2::new(x as f32, y as f32);
+        let (along_x, along_y, twist) = (br - bl, tl - bl, tr - br - tl + bl);
+        let a = twist * step.x * step.y;
+        let b = along_x * step.x + along_y * step.y + twist * (local.x * step.y + local.y * step.x);
+        let c = bl - settings.iso_level + along_x * local.x + along_y * local.y + twist * local.x * local.y;
+        let solid_at = |s: f32| is_solid(settings.iso_level + c + b * s + a * s * s, settings.iso_level, settings.invert);
+        if solid_at(0.0) {
+            return Some(0.0);
+        }
+
+        let mut roots = quadratic_roots(a, b, c);
+        roots.retain(|root| (0.0..=len).contains(root));
+        roots.sort_by(f32::total_cmp);
+        // skip the roots where the ray only touches the level without going in
+        for (i, root) in roots.iter().enumerate(){
+            let until = roots.get(i + 1).copied().unwrap_or(len);
+            if solid_at((root + until) * 0.5) {
+                return Some(*root);
+            }
+        }
+        None
+    }
+}
+
+fn quadratic_roots(a: f32, b: f32, c: f32) -> Vec<f32>{
+    if a == 0.0 {
+        return if b == 0.0 { Vec::new() } else { vec![-c / b] };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return Vec::new();
+    }
+    // the stable form, it doesn't lose the small root when a is tiny
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0.0 {
+        return vec![0.0];
+    }
+    vec![q / a, c / q]
+}
+
+///Raycasts against every IsoField entity, with the same IsoLevel, IsoDistance and IsoInvert
+///overrides the meshes are built with
+#[derive(SystemParam)]
+pub struct IsoRaycast<'w, 's>{
+    fields: Query<'w, 's, RaycastTarget>,
+    iso_level: Res<'w, IsoLevel>,
+    iso_distance: Res<'w, IsoDistance>,
+    invert: Res<'w, IsoInvert>,
+}
+
+// An IsoField entity with its overrides
+#[derive(QueryData)]
+struct RaycastTarget{
+    entity: Entity,
+    field: &'static IsoField,
+    transform: &'static GlobalTransform,
+    level: Option<&'static IsoLevel>,
+    distance: Option<&'static IsoDistance>,
+    invert: Option<&'static IsoInvert>,
+}
+
+impl IsoRaycast<'_, '_>{
+    ///The closest hit of all the fields and the entity it hit
+    pub fn cast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<(Entity, IsoRayHit)>{
+        let mut closest: Option<(Entity, IsoRayHit)> = None;
+        for target in self.fields.iter(){
+            let settings = IsoMeshSettings{
+                iso_level: target.level.unwrap_or(&self.iso_level).0,
+                iso_distance: target.distance.unwrap_or(&self.iso_distance).0,
+                invert: target.invert.unwrap_or(&self.invert).0,
+                ..default()
+            };
+            let max_distance = closest.map_or(max_distance, |(_, hit)| hit.distance);
+            if let Some(hit) = target.field.raycast(origin, direction, max_distance, &settings, target.transform) {
+                if closest.is_none_or(|(_, closest)| hit.distance < closest.distance) {
+                    closest = Some((target.entity, hit));
+                }
+            }
+        }
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    // 10 × 10 world units of samples 0.25 apart, solid in a circle of radius 3 around (5, 5)
+    fn circle() -> (IsoField, IsoMeshSettings){
+        let mut field = IsoField::new((41, 41));
+        for y in 0..41{
+            for x in 0..41{
+                let position = Vec2::new(x as f32, y as f32) * 0.25;
+                field.set(x, y, 3.0 - position.distance(Vec2::splat(5.0)));
+            }
+        }
+        (field, IsoMeshSettings{ iso_level: 0.0, iso_distance: 0.25, ..default() })
+    }
+
+    fn assert_hit(hit: Option<IsoRayHit>, point: Vec2, normal: Vec2, distance: f32){
+        let hit = hit.expect("the ray missed");
+        assert!(hit.point.distance(point) < 0.01, "hit {hit:?}, expected {point}");
+        assert!(hit.normal.dot(normal) > 0.995, "hit {hit:?}, expected normal {normal}");
+        assert!((hit.distance - distance).abs() < 0.01, "hit {hit:?}, expected distance {distance}");
+    }
+
+    #[test]
+    fn hits_circle(){
+        let (field, settings) = circle();
+        let x = 5.0 - (9.0f32 - 0.3 * 0.3).sqrt();
+        let point = Vec2::new(x, 5.3);
+        let hit = field.raycast(Vec2::new(-1.0, 5.3), Vec2::X * 4.0, 100.0, &settings, &GlobalTransform::IDENTITY);
+        assert_hit(hit, point, (point - Vec2::splat(5.0)).normalize(), x + 1.0);
+
+        // diagonally from above, into the top right of the circle
+        let direction = Vec2::new(-1.0, -1.0).normalize();
+        let point = Vec2::splat(5.0) - direction * 3.0;
+        let hit = field.raycast(Vec2::splat(9.5), direction, 100.0, &settings, &GlobalTransform::IDENTITY);
+        assert_hit(hit, point, -direction, (Vec2::splat(9.5) - point).length());
+    }
+
+    #[test]
+    fn misses(){
+        let (field, settings) = circle();
+        let identity = GlobalTransform::IDENTITY;
+        // past the circle, away from it, outside of the field and without a direction
+        assert!(field.raycast(Vec2::new(0.0, 9.0), Vec2::X, 100.0, &settings, &identity).is_none());
+        assert!(field.raycast(Vec2::new(1.0, 5.0), -Vec2::X, 100.0, &settings, &identity).is_none());
+        assert!(field.raycast(Vec2::new(-1.0, 12.0), Vec2::X, 100.0, &settings, &identity).is_none());
+        assert!(field.raycast(Vec2::new(1.0, 5.0), Vec2::ZERO, 100.0, &settings, &identity).is_none());
+    }
+
+    #[test]
+    fn starts_inside(){
+        let (field, settings) = circle();
+        let hit = field.raycast(Vec2::new(5.5, 4.0), Vec2::Y, 100.0, &settings, &GlobalTransform::IDENTITY).unwrap();
+        assert_eq!(hit, IsoRayHit{ point: Vec2::new(5.5, 4.0), normal: -Vec2::Y, distance: 0.0 });
+    }
+
+    #[test]
+    fn along_grid_lines(){
+        let (field, settings) = circle();
+        let identity = GlobalTransform::IDENTITY;
+        // through the center along a row and a column of samples, and along the border of the field
+        assert_hit(field.raycast(Vec2::new(0.0, 5.0), Vec2::X, 100.0, &settings, &identity), Vec2::new(2.0, 5.0), -Vec2::X, 2.0);
+        assert_hit(field.raycast(Vec2::new(5.0, 10.0), -Vec2::Y, 100.0, &settings, &identity), Vec2::new(5.0, 8.0), Vec2::Y, 2.0);
+        assert!(field.raycast(Vec2::new(0.0, 0.0), Vec2::X, 100.0, &settings, &identity).is_none());
+
+        // a field that is solid along its border is hit where the ray enters it
+        let mut settings = settings;
+        settings.iso_level = -10.0;
+        assert_hit(field.raycast(Vec2::new(-2.0, 0.0), Vec2::X, 100.0, &settings, &identity), Vec2::ZERO, -Vec2::X, 2.0);
+    }
+
+    #[test]
+    fn max_distance(){
+        let (field, settings) = circle();
+        let identity = GlobalTransform::IDENTITY;
+        assert!(field.raycast(Vec2::new(0.0, 5.0), Vec2::X, 1.9, &settings, &identity).is_none());
+        assert_hit(field.raycast(Vec2::new(0.0, 5.0), Vec2::X, 2.1, &settings, &identity), Vec2::new(2.0, 5.0), -Vec2::X, 2.0);
+    }
+
+    #[test]
+    fn transformed(){
+        let (field, settings) = circle();
+        // the circle ends up around (90, 10) with a radius of 6
+        let transform = GlobalTransform::from(
+            Transform::from_xyz(100.0, 0.0, 0.0)
+                .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2))
+                .with_scale(Vec3::splat(2.0))
+        );
+        let point = Vec2::new(90.6, 10.0 - (36.0f32 - 0.6 * 0.6).sqrt());
+        let hit = field.raycast(Vec2::new(90.6, -5.0), Vec2::Y, 100.0, &settings, &transform);
+        assert_hit(hit, point, (point - Vec2::new(90.0, 10.0)).normalize(), point.y + 5.0);
+    }
+
+    #[test]
+    fn closest_entity(){
+        let mut world = World::new();
+        world.init_resource::<IsoLevel>();
+        world.init_resource::<IsoDistance>();
+        world.init_resource::<IsoInvert>();
+        let (field, _) = circle();
+        let mut spawn = |x: f32| world.spawn((
+            field.clone(),
+            IsoLevel(0.0),
+            IsoDistance(0.25),
+            GlobalTransform::from_xyz(x, 0.0, 0.0),
+        )).id();
+        // circles around x = 5, x = 1 and x = 25
+        spawn(0.0);
+        let closest = spawn(-4.0);
+        spawn(20.0);
+
+        let (entity, hit) = world.run_system_once(|raycast: IsoRaycast| raycast.cast(Vec2::new(-10.0, 5.0), Vec2::X, 100.0)).unwrap();
+        assert_eq!(entity, closest);
+        assert!((hit.distance - 8.0).abs() < 0.01, "{hit:?}");
+        assert!(world.run_system_once(|raycast: IsoRaycast| raycast.cast(Vec2::new(-10.0, 5.0), Vec2::X, 7.9)).is_none());
+    }
+}
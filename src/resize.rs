@@ -0,0 +1,181 @@
+use crate::{IsoField, IsoFieldError, Size};
+
+///The part of an IsoField that stays in place when it is resized
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IsoAnchor{
+    #[default]
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight,
+    ///An odd difference is added or removed on the top and right
+    Center,
+}
+
+impl IsoField{
+    ///Grows or shrinks the field to `size`, the samples at `anchor` keep their place.
+    ///New samples are set to `fill` and material 0
+    pub fn resize(&mut self, size: impl Into<Size>, anchor: IsoAnchor, fill: f32){
+        let (width, height): Size = size.into();
+        let grow_x = width as isize - self.width() as isize;
+        let grow_y = height as isize - self.height() as isize;
+        let offset = match anchor {
+            IsoAnchor::BottomLeft => (0, 0),
+            IsoAnchor::BottomRight => (grow_x, 0),
+            IsoAnchor::TopLeft => (0, grow_y),
+            IsoAnchor::TopRight => (grow_x, grow_y),
+            IsoAnchor::Center => (grow_x / 2, grow_y / 2),
+        };
+        self.remap((width, height), offset, fill);
+    }
+
+    ///Keeps only the `size` samples starting at `min`, which becomes the new (0, 0)
+    pub fn crop(&mut self, min: (usize, usize), size: impl Into<Size>) -> Result<(), IsoFieldError>{
+        let (width, height): Size = size.into();
+        if width == 0 || height == 0 {
+            return Err(IsoFieldError::Empty);
+        }
+        // the last sample kept, a size running past usize::MAX is out of bounds as well
+        match (min.0.checked_add(width - 1), min.1.checked_add(height - 1)) {
+            (Some(x), Some(y)) if x < self.width() && y < self.height() => {},
+            (x, y) => return Err(IsoFieldError::OutOfBounds{
+                x: x.unwrap_or(usize::MAX),
+                y: y.unwrap_or(usize::MAX),
+                width: self.width(),
+                height: self.height(),
+            }),
+        }
+        self.remap((width, height), (-(min.0 as isize), -(min.1 as isize)), 0.0);
+        Ok(())
+    }
+
+    ///Adds rows and columns of `fill` around the field
+    pub fn pad(&mut self, left: usize, right: usize, bottom: usize, top: usize, fill: f32){
+        let size = (self.width() + left + right, self.height() + bottom + top);
+        self.remap(size, (left as isize, bottom as isize), fill);
+    }
+
+    // Rebuilds the field at `size`, the old sample (x, y) moves to (x, y) + offset
+    fn remap(&mut self, (width, height): Size, offset: (isize, isize), fill: f32){
+        let old_at = |x: usize, y: usize| {
+            let old_x = x as isize - offset.0;
+            let old_y = y as isize - offset.1;
+            let inside = old_x >= 0 && old_y >= 0 && (old_x as usize) < self.width() && (old_y as usize) < self.height();
+            inside.then(|| self.index(old_x as usize, old_y as usize))
+        };
+        let mut field = Vec::with_capacity(width * height);
+        let mut materials = Vec::new();
+        for y in 0..height{
+            for x in 0..width{
+                field.push(old_at(x, y).map_or(fill, |index| self.field[index]));
+            }
+        }
+        if !self.materials.is_empty() {
+            materials.reserve(width * height);
+            for y in 0..height{
+                for x in 0..width{
+                    materials.push(old_at(x, y).map_or(0, |index| self.materials[index]));
+                }
+            }
+        }
+        self.x_size = width;
+        self.field = field;
+        self.materials = materials;
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    // sample (x, y) is x + 10 y
+    fn numbered(width: usize, height: usize) -> IsoField{
+        IsoField::new_from((width, height), (0..width * height).map(|i| (i % width + i / width * 10) as f32).collect())
+    }
+
+    // the rows from the bottom up
+    fn rows(field: &IsoField) -> Vec<Vec<f32>>{
+        field.values().chunks(field.width()).map(|row| row.to_vec()).collect()
+    }
+
+    fn resized(size: Size, anchor: IsoAnchor) -> Vec<Vec<f32>>{
+        let mut field = numbered(4, 3);
+        field.resize(size, anchor, -1.0);
+        assert_eq!((field.width(), field.height()), size);
+        rows(&field)
+    }
+
+    #[test]
+    fn grow(){
+        let e = -1.0;
+        assert_eq!(resized((5, 4), IsoAnchor::BottomLeft), [
+            vec![0.0, 1.0, 2.0, 3.0, e], vec![10.0, 11.0, 12.0, 13.0, e], vec![20.0, 21.0, 22.0, 23.0, e], vec![e; 5],
+        ]);
+        assert_eq!(resized((5, 4), IsoAnchor::BottomRight), [
+            vec![e, 0.0, 1.0, 2.0, 3.0], vec![e, 10.0, 11.0, 12.0, 13.0], vec![e, 20.0, 21.0, 22.0, 23.0], vec![e; 5],
+        ]);
+        assert_eq!(resized((5, 4), IsoAnchor::TopLeft), [
+            vec![e; 5], vec![0.0, 1.0, 2.0, 3.0, e], vec![10.0, 11.0, 12.0, 13.0, e], vec![20.0, 21.0, 22.0, 23.0, e],
+        ]);
+        assert_eq!(resized((5, 4), IsoAnchor::TopRight), [
+            vec![e; 5], vec![e, 0.0, 1.0, 2.0, 3.0], vec![e, 10.0, 11.0, 12.0, 13.0], vec![e, 20.0, 21.0, 22.0, 23.0],
+        ]);
+        // odd growth goes on the top and right
+        assert_eq!(resized((5, 4), IsoAnchor::Center), resized((5, 4), IsoAnchor::BottomLeft));
+        assert_eq!(resized((7, 6), IsoAnchor::Center), [
+            vec![e; 7],
+            vec![e, 0.0, 1.0, 2.0, 3.0, e, e],
+            vec![e, 10.0, 11.0, 12.0, 13.0, e, e],
+            vec![e, 20.0, 21.0, 22.0, 23.0, e, e],
+            vec![e; 7],
+            vec![e; 7],
+        ]);
+    }
+
+    #[test]
+    fn shrink(){
+        assert_eq!(resized((1, 1), IsoAnchor::BottomLeft), [[0.0]]);
+        assert_eq!(resized((1, 1), IsoAnchor::BottomRight), [[3.0]]);
+        assert_eq!(resized((1, 1), IsoAnchor::TopLeft), [[20.0]]);
+        assert_eq!(resized((1, 1), IsoAnchor::TopRight), [[23.0]]);
+        assert_eq!(resized((1, 1), IsoAnchor::Center), [[11.0]]);
+        // odd shrinking takes from the top and right
+        assert_eq!(resized((2, 2), IsoAnchor::Center), [[1.0, 2.0], [11.0, 12.0]]);
+        assert_eq!(resized((4, 2), IsoAnchor::TopRight), [[10.0, 11.0, 12.0, 13.0], [20.0, 21.0, 22.0, 23.0]]);
+    }
+
+    #[test]
+    fn materials_move_with_the_samples(){
+        let mut field = numbered(2, 2);
+        field.set_materials(vec![1, 2, 3, 4]).unwrap();
+        field.resize((3, 3), IsoAnchor::TopRight, 0.0);
+        assert_eq!(field.materials(), [0, 0, 0, 0, 1, 2, 0, 3, 4]);
+        field.pad(0, 1, 1, 0, 0.0);
+        assert_eq!(field.materials(), [0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 3, 4, 0]);
+    }
+
+    #[test]
+    fn pad(){
+        let mut field = numbered(2, 1);
+        field.pad(1, 2, 0, 1, 9.0);
+        assert_eq!(rows(&field), [[9.0, 0.0, 1.0, 9.0, 9.0], [9.0; 5]]);
+        field.pad(0, 0, 2, 0, 5.0);
+        assert_eq!(rows(&field), [vec![5.0; 5], vec![5.0; 5], vec![9.0, 0.0, 1.0, 9.0, 9.0], vec![9.0; 5]]);
+        field.pad(0, 0, 0, 0, 5.0);
+        assert_eq!((field.width(), field.height()), (5, 4));
+    }
+
+    #[test]
+    fn crop(){
+        let mut field = numbered(4, 3);
+        field.crop((1, 1), (3, 2)).unwrap();
+        assert_eq!(rows(&field), [[11.0, 12.0, 13.0], [21.0, 22.0, 23.0]]);
+
+        let mut field = numbered(4, 3);
+        assert_eq!(field.crop((0, 0), (0, 2)), Err(IsoFieldError::Empty));
+        assert_eq!(field.crop((2, 0), (3, 1)), Err(IsoFieldError::OutOfBounds{ x: 4, y: 0, width: 4, height: 3 }));
+        assert_eq!(field.crop((usize::MAX, 0), (2, 1)), Err(IsoFieldError::OutOfBounds{ x: usize::MAX, y: 0, width: 4, height: 3 }));
+        assert_eq!(field.crop((0, usize::MAX - 1), (1, 5)), Err(IsoFieldError::OutOfBounds{ x: 0, y: usize::MAX, width: 4, height: 3 }));
+        assert_eq!(rows(&field), rows(&numbered(4, 3)));
+    }
+}
@@ -0,0 +1,349 @@
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+
+use crate::{IsoDistance, IsoField, IsoFieldAsset, IsoFieldError, IsoLevel};
+
+// Layout, numbers are little endian and varints are LEB128:
+// - the magic and the version byte
+// - a flags byte, see below
+// - width and height as u32
+// - IsoLevel and IsoDistance as f32, each only when its flag is set
+// - the samples as varints of the value's bits XORed with the previous value, so close
+//   values stay short, shifted up one bit. The low bit is set when a varint count of
+//   further repeats of the value follows
+// - when the materials flag is set, the materials as runs of a varint length and the material
+const MAGIC: &[u8; 4] = b"ISOF";
+const HAS_LEVEL: u8 = 1;
+const HAS_DISTANCE: u8 = 2;
+const HAS_MATERIALS: u8 = 4;
+
+///The format version IsoFieldAsset::to_bytes writes, older versions keep loading
+pub const ISO_FIELD_VERSION: u8 = 1;
+
+///The most samples to_bytes writes and from_bytes reads, 8192 × 8192. A few bytes of runs can
+///claim any size, so bigger headers are treated as corrupt instead of allocating for them
+pub const ISO_FIELD_MAX_SAMPLES: usize = 1 << 26;
+
+///Why saved IsoField data couldn't be read
+#[derive(Debug)]
+pub enum IsoSaveError{
+    Io(std::io::Error),
+    ///The data doesn't start with the IsoField magic
+    NotAnIsoField,
+    ///The data was written by a newer version of the format, or has version 0
+    UnsupportedVersion(u8),
+    ///The data ends early or doesn't add up to the size of the field
+    Corrupt,
+    ///The field is over ISO_FIELD_MAX_SAMPLES or a side doesn't fit in a u32, so it can't be saved
+    TooLarge{ width: usize, height: usize },
+    Field(IsoFieldError),
+}
+
+impl std::fmt::Display for IsoSaveError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        match self {
+            Self::Io(error) => write!(f, "could not read iso field: {error}"),
+            Self::NotAnIsoField => write!(f, "the data is not an iso field"),
+            Self::UnsupportedVersion(version) => write!(f, "iso field version {version} is not supported, the latest is {ISO_FIELD_VERSION}"),
+            Self::Corrupt => write!(f, "the iso field data is corrupt"),
+            Self::TooLarge{ width, height } => write!(f, "the {width}x{height} iso field is too large to save"),
+            Self::Field(error) => write!(f, "invalid iso field: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for IsoSaveError{}
+
+impl From<std::io::Error> for IsoSaveError{
+    fn from(error: std::io::Error) -> Self{
+        Self::Io(error)
+    }
+}
+
+impl From<IsoFieldError> for IsoSaveError{
+    fn from(error: IsoFieldError) -> Self{
+        Self::Field(error)
+    }
+}
+
+impl IsoFieldAsset{
+    ///The field, its materials and the IsoLevel and IsoDistance in the compressed
+    ///binary format, write it to a `.isofield` file to load it with the asset server.
+    ///Fields from_bytes would reject as too large are TooLarge instead of being written
+    pub fn to_bytes(&self) -> Result<Vec<u8>, IsoSaveError>{
+        let (width, height) = saved_size(self.field.width(), self.field.height())?;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(ISO_FIELD_VERSION);
+        let mut flags = 0;
+        if self.iso_level.is_some() {
+            flags |= HAS_LEVEL;
+        }
+        if self.iso_distance.is_some() {
+            flags |= HAS_DISTANCE;
+        }
+        if !self.field.materials().is_empty() {
+            flags |= HAS_MATERIALS;
+        }
+        bytes.push(flags);
+        bytes.extend_from_slice(&width.to_le_bytes());
+        bytes.extend_from_slice(&height.to_le_bytes());
+        if let Some(IsoLevel(level)) = self.iso_level {
+            bytes.extend_from_slice(&level.to_le_bytes());
+        }
+        if let Some(IsoDistance(distance)) = self.iso_distance {
+            bytes.extend_from_slice(&distance.to_le_bytes());
+        }
+
+        let mut previous = 0;
+        for (len, value) in runs(self.field.values().iter().map(|value| value.to_bits())){
+            let repeats = len - 1;
+            write_varint(&mut bytes, ((value ^ previous) as u64) << 1 | (repeats > 0) as u64);
+            if repeats > 0 {
+                write_varint(&mut bytes, repeats as u64);
+            }
+            previous = value;
+        }
+        if flags & HAS_MATERIALS != 0 {
+            for (len, material) in runs(self.field.materials().iter().copied()){
+                write_varint(&mut bytes, len as u64);
+                bytes.push(material);
+            }
+        }
+        Ok(bytes)
+    }
+
+    ///Reads data written by to_bytes, fields over ISO_FIELD_MAX_SAMPLES are Corrupt
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, IsoSaveError>{
+        let mut reader = ByteReader{ bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(IsoSaveError::NotAnIsoField);
+        }
+        let version = reader.byte()?;
+        if version == 0 || version > ISO_FIELD_VERSION {
+            return Err(IsoSaveError::UnsupportedVersion(version));
+        }
+        let flags = reader.byte()?;
+        let width = reader.u32()? as usize;
+        let height = reader.u32()? as usize;
+        let len = width.checked_mul(height).filter(|len| *len <= ISO_FIELD_MAX_SAMPLES).ok_or(IsoSaveError::Corrupt)?;
+        let iso_level = if flags & HAS_LEVEL != 0 { Some(IsoLevel(reader.f32()?)) } else { None };
+        let iso_distance = if flags & HAS_DISTANCE != 0 { Some(IsoDistance(reader.f32()?)) } else { None };
+
+        let mut values = Vec::new();
+        let mut previous = 0;
+        while values.len() < len {
+            let token = reader.varint()?;
+            let bits = u32::try_from(token >> 1).map_err(|_| IsoSaveError::Corrupt)? ^ previous;
+            let run = if token & 1 != 0 { 1 + reader.run(len - values.len() - 1)? } else { 1 };
+            values.resize(values.len() + run, f32::from_bits(bits));
+            previous = bits;
+        }
+        let mut materials = Vec::new();
+        if flags & HAS_MATERIALS != 0 {
+            while materials.len() < len {
+                let run = reader.run(len - materials.len())?;
+                let material = reader.byte()?;
+                materials.resize(materials.len() + run, material);
+            }
+        }
+
+        let mut field = if len == 0 { IsoField::new((width, height)) } else { IsoField::try_new_from((width, height), values)? };
+        field.set_materials(materials)?;
+        Ok(Self{ field, iso_level, iso_distance })
+    }
+}
+
+///Loads `.isofield` files written with IsoFieldAsset::to_bytes
+#[derive(Default)]
+pub struct IsoFieldLoader;
+
+impl AssetLoader for IsoFieldLoader{
+    type Asset = IsoFieldAsset;
+    type Settings = ();
+    type Error = IsoSaveError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<IsoFieldAsset, IsoSaveError>{
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        IsoFieldAsset::from_bytes(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str]{
+        &["isofield"]
+    }
+}
+
+// the width and height as they are saved, when from_bytes can read them back
+fn saved_size(width: usize, height: usize) -> Result<(u32, u32), IsoSaveError>{
+    let too_large = || IsoSaveError::TooLarge{ width, height };
+    if !matches!(width.checked_mul(height), Some(len) if len <= ISO_FIELD_MAX_SAMPLES) {
+        return Err(too_large());
+    }
+    Ok((u32::try_from(width).map_err(|_| too_large())?, u32::try_from(height).map_err(|_| too_large())?))
+}
+
+// the lengths of the runs of equal items
+fn runs<T: PartialEq + Copy>(items: impl Iterator<Item = T>) -> Vec<(usize, T)>{
+    let mut runs: Vec<(usize, T)> = Vec::new();
+    for item in items{
+        match runs.last_mut() {
+            Some((len, last)) if *last == item => *len += 1,
+            _ => runs.push((1, item)),
+        }
+    }
+    runs
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64){
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+struct ByteReader<'a>{
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a>{
+    fn take(&mut self, len: usize) -> Result<&'a [u8], IsoSaveError>{
+        if self.bytes.len() < len {
+            return Err(IsoSaveError::Corrupt);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, IsoSaveError>{
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, IsoSaveError>{
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, IsoSaveError>{
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn varint(&mut self) -> Result<u64, IsoSaveError>{
+        let mut value = 0;
+        for shift in (0..64).step_by(7){
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(IsoSaveError::Corrupt)
+    }
+
+    // a positive run length that has to fit in the `left` samples
+    fn run(&mut self, left: usize) -> Result<usize, IsoSaveError>{
+        match usize::try_from(self.varint()?) {
+            Ok(run) if run > 0 && run <= left => Ok(run),
+            _ => Err(IsoSaveError::Corrupt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn header(version: u8, width: u32, height: u32) -> Vec<u8>{
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[version, 0]);
+        bytes.extend_from_slice(&width.to_le_bytes());
+        bytes.extend_from_slice(&height.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn round_trip(){
+        let special = [f32::NAN, -f32::NAN, f32::from_bits(0x7fc0_1234), -0.0, 0.0, f32::INFINITY, f32::MIN_POSITIVE];
+        let values: Vec<f32> = (0..48).map(|i| if i < 14 { special[i / 2] } else { (i / 5) as f32 * 0.25 }).collect();
+        let mut field = IsoField::new_from((8, 6), values.clone());
+        field.set_materials((0..48).map(|i| (i / 7) as u8).collect()).unwrap();
+        let asset = IsoFieldAsset::new(field).with_iso_level(IsoLevel(0.3)).with_iso_distance(IsoDistance(-2.5));
+
+        let loaded = IsoFieldAsset::from_bytes(&asset.to_bytes().unwrap()).unwrap();
+        assert_eq!((loaded.field.width(), loaded.field.height()), (8, 6));
+        let bits = |values: &[f32]| values.iter().map(|value| value.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(loaded.field.values()), bits(&values));
+        assert_eq!(loaded.field.materials(), asset.field.materials());
+        assert_eq!(loaded.iso_level.map(|level| level.0), Some(0.3));
+        assert_eq!(loaded.iso_distance.map(|distance| distance.0), Some(-2.5));
+    }
+
+    #[test]
+    fn round_trip_without_extras(){
+        for (width, height) in [(0, 0), (3, 0), (1, 1), (5, 4)]{
+            let asset = IsoFieldAsset::new(IsoField::new((width, height)));
+            let loaded = IsoFieldAsset::from_bytes(&asset.to_bytes().unwrap()).unwrap();
+            assert_eq!((loaded.field.width(), loaded.field.height()), (width, height));
+            assert_eq!(loaded.field.values(), asset.field.values());
+            assert!(loaded.field.materials().is_empty());
+            assert!(loaded.iso_level.is_none() && loaded.iso_distance.is_none());
+        }
+    }
+
+    #[test]
+    fn truncated(){
+        let mut field = IsoField::new_from((4, 4), (0..16).map(|i| i as f32).collect());
+        field.set_materials(vec![3; 16]).unwrap();
+        let bytes = IsoFieldAsset::new(field).with_iso_level(IsoLevel(1.0)).to_bytes().unwrap();
+        for len in 0..bytes.len(){
+            assert!(IsoFieldAsset::from_bytes(&bytes[..len]).is_err(), "{len} of {} bytes loaded", bytes.len());
+        }
+    }
+
+    #[test]
+    fn corrupt(){
+        let bytes = IsoFieldAsset::new(IsoField::new((2, 2))).to_bytes().unwrap();
+        assert!(matches!(IsoFieldAsset::from_bytes(b"PNG\0 not a field"), Err(IsoSaveError::NotAnIsoField)));
+        for version in [0, ISO_FIELD_VERSION + 1]{
+            let mut bytes = bytes.clone();
+            bytes[MAGIC.len()] = version;
+            assert!(matches!(IsoFieldAsset::from_bytes(&bytes), Err(IsoSaveError::UnsupportedVersion(v)) if v == version));
+        }
+
+        // sizes that would need huge allocations
+        for (width, height) in [(u32::MAX, u32::MAX), (1 << 14, 1 << 13), (u32::MAX, 2)]{
+            let mut bytes = header(ISO_FIELD_VERSION, width, height);
+            bytes.extend_from_slice(&[1, 0xff, 0xff, 0xff, 0xff, 0x0f]);
+            assert!(matches!(IsoFieldAsset::from_bytes(&bytes), Err(IsoSaveError::Corrupt)));
+        }
+
+        // a run longer than the field, and a zero length material run
+        let mut bytes = header(ISO_FIELD_VERSION, 2, 2);
+        bytes.extend_from_slice(&[1, 4]);
+        assert!(matches!(IsoFieldAsset::from_bytes(&bytes), Err(IsoSaveError::Corrupt)));
+        let mut bytes = header(ISO_FIELD_VERSION, 2, 2);
+        bytes[MAGIC.len() + 1] = HAS_MATERIALS;
+        bytes.extend_from_slice(&[1, 3, 0, 1]);
+        assert!(matches!(IsoFieldAsset::from_bytes(&bytes), Err(IsoSaveError::Corrupt)));
+    }
+
+    #[test]
+    fn too_large_to_save(){
+        // 10000 × 10000 is over the cap, check the size without allocating the samples
+        assert!(matches!(saved_size(10000, 10000), Err(IsoSaveError::TooLarge{ width: 10000, height: 10000 })));
+        assert!(matches!(saved_size(usize::MAX, 2), Err(IsoSaveError::TooLarge{ .. })));
+        assert_eq!(saved_size(8192, 8192).unwrap(), (8192, 8192));
+
+        // an empty field whose width doesn't fit in the u32 header
+        #[cfg(target_pointer_width = "64")]
+        {
+            let asset = IsoFieldAsset::new(IsoField::new((1usize << 32, 0)));
+            assert!(matches!(asset.to_bytes(), Err(IsoSaveError::TooLarge{ .. })));
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use bevy::prelude::*;
+
+use crate::{IsoField, IsoMeshSettings};
+
+///A shape tree described by its signed distance, negative inside.
+///Positions and sizes are in the units of the mesh, so a sample sits every IsoDistance
+#[derive(Debug, Clone, PartialEq)]
+pub enum IsoShape{
+    Circle{ center: Vec2, radius: f32 },
+    Rect{ center: Vec2, half_size: Vec2 },
+    ///A line with round ends `radius` wide
+    Capsule{ start: Vec2, end: Vec2, radius: f32 },
+    ///A line without width, it has no inside so it is only useful with smooth_union
+    ///or when rasterized with a higher iso level
+    Segment{ start: Vec2, end: Vec2 },
+    ///Any simple polygon, the points can go either way round
+    Polygon(Vec<Vec2>),
+    Union(Vec<IsoShape>),
+    Intersection(Vec<IsoShape>),
+    ///The first shape with the second one cut out
+    Difference(Box<IsoShape>, Box<IsoShape>),
+    ///A union that rounds the seams between the shapes over `radius`
+    SmoothUnion{ shapes: Vec<IsoShape>, radius: f32 },
+}
+
+impl IsoShape{
+    pub fn union(self, other: IsoShape) -> Self{
+        match self {
+            Self::Union(mut shapes) => {
+                shapes.push(other);
+                Self::Union(shapes)
+            }
+            shape => Self::Union(vec![shape, other]),
+        }
+    }
+
+    pub fn intersect(self, other: IsoShape) -> Self{
+        match self {
+            Self::Intersection(mut shapes) => {
+                shapes.push(other);
+                Self::Intersection(shapes)
+            }
+            shape => Self::Intersection(vec![shape, other]),
+        }
+    }
+
+    pub fn subtract(self, other: IsoShape) -> Self{
+        Self::Difference(Box::new(self), Box::new(other))
+    }
+
+    pub fn smooth_union(self, other: IsoShape, radius: f32) -> Self{
+        Self::SmoothUnion{ shapes: vec![self, other], radius }
+    }
+
+    ///The signed distance from `point` to the outline, negative inside.
+    ///The combined shapes give a bound rather than the exact distance away from the outline
+    pub fn distance(&self, point: Vec2) -> f32{
+        match self {
+            Self::Circle{ center, radius } => point.distance(*center) - radius,
+            Self::Rect{ center, half_size } => {
+                let d = (point - *center).abs() - *half_size;
+                d.max(Vec2::ZERO).length() + d.x.max(d.y).min(0.0)
+            }
+            Self::Capsule{ start, end, radius } => segment_distance(point, *start, *end) - radius,
+            Self::Segment{ start, end } => segment_distance(point, *start, *end),
+            Self::Polygon(points) => polygon_distance(point, points),
+            Self::Union(shapes) => shapes
+                .iter()
+                .map(|shape| shape.distance(point))
+                .fold(f32::MAX, f32::min),
+            Self::Intersection(shapes) => shapes
+                .iter()
+                .map(|shape| shape.distance(point))
+                .fold(-f32::MAX, f32::max),
+            Self::Difference(shape, cut) => shape.distance(point).max(-cut.distance(point)),
+            Self::SmoothUnion{ shapes, radius } => shapes
+                .iter()
+                .map(|shape| shape.distance(point))
+                .reduce(|a, b| smooth_min(a, b, *radius))
+                .unwrap_or(f32::MAX),
+        }
+    }
+}
+
+impl IsoField{
+    ///Writes the shape into every sample, sample (x, y) sits at (x, y) * iso_distance.
+    ///The values are `iso_level - distance` (`iso_level + distance` when inverted),
+    ///so linear interpolation puts the mesh edge right on the outline
+    pub fn rasterize(&mut self, shape: &IsoShape, settings: &IsoMeshSettings){
+        for y in 0..self.height(){
+            for x in 0..self.width(){
+                let distance = shape.distance(Vec2::new(x as f32, y as f32) * settings.iso_distance);
+                let value = if settings.invert {
+                    settings.iso_level + distance
+                } else {
+                    settings.iso_level - distance
+                };
+                self.set(x, y, value);
+            }
+        }
+    }
+}
+
+fn segment_distance(point: Vec2, start: Vec2, end: Vec2) -> f32{
+    let along = end - start;
+    let t = ((point - start).dot(along) / along.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+    point.distance(start + along * t)
+}
+
+// the distance to the closest edge, negative when an odd number of edges are crossed
+// going right from the point
+fn polygon_distance(point: Vec2, points: &[Vec2]) -> f32{
+    if points.len() < 3 {
+        return f32::MAX;
+    }
+    let mut distance = f32::MAX;
+    let mut inside = false;
+    for (i, start) in points.iter().enumerate(){
+        let end = points[(i + 1) % points.len()];
+        distance = distance.min(segment_distance(point, *start, end));
+        if (start.y > point.y) != (end.y > point.y) {
+            let crossing_x = start.x + (point.y - start.y) / (end.y - start.y) * (end.x - start.x);
+            if point.x < crossing_x {
+                inside = !inside;
+            }
+        }
+    }
+    if inside { -distance } else { distance }
+}
+
+// polynomial smooth minimum, equal to min away from the seam
+fn smooth_min(a: f32, b: f32, radius: f32) -> f32{
+    if radius <= 0.0 {
+        return a.min(b);
+    }
+    let h = (0.5 + 0.5 * (b - a) / radius).clamp(0.0, 1.0);
+    b + (a - b) * h - radius * h * (1.0 - h)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32, what: &str){
+        assert!((actual - expected).abs() < 1e-5, "{what}: {actual} instead of {expected}");
+    }
+
+    #[test]
+    fn primitives(){
+        let circle = IsoShape::Circle{ center: Vec2::new(1.0, 1.0), radius: 2.0 };
+        assert_close(circle.distance(Vec2::new(1.0, 1.0)), -2.0, "circle center");
+        assert_close(circle.distance(Vec2::new(3.0, 1.0)), 0.0, "circle outline");
+        assert_close(circle.distance(Vec2::new(1.0, 6.0)), 3.0, "circle outside");
+
+        let rect = IsoShape::Rect{ center: Vec2::ZERO, half_size: Vec2::new(2.0, 1.0) };
+        assert_close(rect.distance(Vec2::ZERO), -1.0, "rect center");
+        assert_close(rect.distance(Vec2::new(1.5, 0.0)), -0.5, "rect inside");
+        assert_close(rect.distance(Vec2::new(2.0, 0.5)), 0.0, "rect side");
+        assert_close(rect.distance(Vec2::new(5.0, 0.0)), 3.0, "rect outside");
+        assert_close(rect.distance(Vec2::new(5.0, 5.0)), 5.0, "rect corner");
+
+        let segment = IsoShape::Segment{ start: Vec2::ZERO, end: Vec2::new(4.0, 0.0) };
+        assert_close(segment.distance(Vec2::new(2.0, 0.0)), 0.0, "on the segment");
+        assert_close(segment.distance(Vec2::new(2.0, -3.0)), 3.0, "beside the segment");
+        assert_close(segment.distance(Vec2::new(7.0, 4.0)), 5.0, "past the end");
+
+        let capsule = IsoShape::Capsule{ start: Vec2::ZERO, end: Vec2::new(4.0, 0.0), radius: 1.0 };
+        assert_close(capsule.distance(Vec2::new(2.0, 0.0)), -1.0, "capsule middle");
+        assert_close(capsule.distance(Vec2::new(-2.0, 0.0)), 1.0, "capsule end");
+
+        // clockwise, the sign doesn't depend on the winding
+        let triangle = IsoShape::Polygon(vec![Vec2::ZERO, Vec2::new(0.0, 4.0), Vec2::new(4.0, 0.0)]);
+        assert_close(triangle.distance(Vec2::new(1.0, 1.0)), -1.0, "inside the polygon");
+        assert_close(triangle.distance(Vec2::new(-2.0, 1.0)), 2.0, "outside the polygon");
+    }
+
+    #[test]
+    fn combined(){
+        let left = IsoShape::Circle{ center: Vec2::new(-1.0, 0.0), radius: 1.5 };
+        let right = IsoShape::Circle{ center: Vec2::new(1.0, 0.0), radius: 1.5 };
+        let points = [Vec2::ZERO, Vec2::new(-2.0, 0.0), Vec2::new(2.0, 0.0), Vec2::new(0.0, 3.0), Vec2::new(-4.0, 1.0)];
+        for point in points{
+            let (a, b) = (left.distance(point), right.distance(point));
+            assert_close(left.clone().union(right.clone()).distance(point), a.min(b), "union");
+            assert_close(left.clone().intersect(right.clone()).distance(point), a.max(b), "intersection");
+            assert_close(left.clone().subtract(right.clone()).distance(point), a.max(-b), "difference");
+        }
+        assert!(left.clone().union(right.clone()).distance(Vec2::new(2.0, 0.0)) < 0.0);
+        assert!(left.clone().intersect(right.clone()).distance(Vec2::new(2.0, 0.0)) > 0.0);
+        assert!(left.clone().intersect(right.clone()).distance(Vec2::ZERO) < 0.0);
+        assert!(left.clone().subtract(right.clone()).distance(Vec2::new(-2.0, 0.0)) < 0.0);
+        assert!(left.clone().subtract(right.clone()).distance(Vec2::ZERO) > 0.0);
+
+        // chained unions and intersections stay flat
+        let three = left.clone().union(right.clone()).union(IsoShape::Circle{ center: Vec2::ZERO, radius: 1.0 });
+        assert!(matches!(&three, IsoShape::Union(shapes) if shapes.len() == 3));
+    }
+
+    #[test]
+    fn smooth_union(){
+        let left = IsoShape::Circle{ center: Vec2::new(-2.0, 0.0), radius: 1.5 };
+        let right = IsoShape::Circle{ center: Vec2::new(2.0, 0.0), radius: 1.5 };
+        let smooth = left.clone().smooth_union(right.clone(), 1.0);
+        let union = left.clone().union(right.clone());
+        // the seam between the circles is filled in
+        assert_close(union.distance(Vec2::ZERO), 0.5, "union seam");
+        assert_close(smooth.distance(Vec2::ZERO), 0.25, "smooth seam");
+        // away from the seam it is a plain union
+        for point in [Vec2::new(-3.0, 0.0), Vec2::new(3.5, 0.0), Vec2::new(2.0, 2.0)]{
+            assert_close(smooth.distance(point), union.distance(point), "away from the seam");
+        }
+        assert_close(left.clone().smooth_union(right, 0.0).distance(Vec2::ZERO), 0.5, "no radius");
+    }
+
+    #[test]
+    fn rasterize_zero_crossing(){
+        let shape = IsoShape::Circle{ center: Vec2::ZERO, radius: 2.6 };
+        for invert in [false, true]{
+            let settings = IsoMeshSettings{ iso_distance: 0.5, invert, ..default() };
+            let mut field = IsoField::new((12, 2));
+            field.rasterize(&shape, &settings);
+            let solid = |x: usize| (field.get(x, 0) > settings.iso_level) != invert;
+            // the outline is at 2.6, between samples 5 and 6 at 2.5 and 3
+            assert!((0..=5).all(solid), "invert {invert}");
+            assert!(!(6..12).any(solid), "invert {invert}");
+            let (a, b) = (field.get(5, 0), field.get(6, 0));
+            let t = (settings.iso_level - a) / (b - a);
+            assert_close((5.0 + t) * settings.iso_distance, 2.6, "crossing");
+        }
+    }
+}
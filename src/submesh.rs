@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+use bevy::{
+    ecs::query::QueryFilter,
+    render::{
+        mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology
+    }, sprite::Mesh2dHandle, utils::HashMap,
+};
+
+use crate::UvGenerator;
+use crate::color::{vertex_colors, IsoGradient};
+
+///A child entity holding one of the meshes of a split IsoField (a band or a material)
+#[derive(Component, Debug)]
+pub struct IsoSubMesh(pub usize);
+
+// The submesh children of an IsoField, in submesh order
+#[derive(Component)]
+pub(crate) struct IsoSubMeshes(Vec<Entity>);
+
+// Puts the new meshes on the existing children, or respawns them when the count changed
+pub(crate) fn sync_submeshes<M: Asset, F: QueryFilter>(
+    commands: &mut Commands,
+    entity: Entity,
+    children: Option<&IsoSubMeshes>,
+    sub_meshes: Vec<(Mesh, Handle<M>)>,
+    sub_mesh_q: &mut Query<(&mut Mesh2dHandle, &mut Handle<M>), F>,
+    meshes: &mut Assets<Mesh>,
+){
+    if let Some(children) = children.filter(|children| children.0.len() == sub_meshes.len()){
+        for (child, (mesh, material)) in children.0.iter().zip(sub_meshes){
+            let Ok((mut mesh_2d, mut handle)) = sub_mesh_q.get_mut(*child) else {
+                continue;
+            };
+            if let Some(stored_mesh) = meshes.get_mut(&mut mesh_2d.0){
+                *stored_mesh = mesh;
+            }
+            *handle = material;
+        }
+        return;
+    }
+
+    if let Some(children) = children{
+        for child in children.0.iter(){
+            commands.entity(*child).despawn_recursive();
+        }
+    }
+    let mut new_children = Vec::new();
+    for (index, (mesh, material)) in sub_meshes.into_iter().enumerate(){
+        let child = commands.spawn((
+            IsoSubMesh(index),
+            Mesh2dHandle(meshes.add(mesh)),
+            material,
+            SpatialBundle::default(),
+        )).id();
+        new_children.push(child);
+    }
+    commands.entity(entity)
+        .push_children(&new_children)
+        .insert(IsoSubMeshes(new_children));
+}
+
+// Points closer than this, in field units, become one vertex
+const WELD_DISTANCE: f32 = 1e-4;
+
+// Collects convex polygons into a mesh, welding points that are closer than WELD_DISTANCE.
+// Unlike build_mesh this doesn't key vertices by grid corner and edge: a band edge
+// can be crossed once per threshold and the material quadrants have points inside
+// the cells, so these meshes are still welded by position
+#[derive(Default)]
+pub(crate) struct PolygonMeshBuilder{
+    // vertices by the WELD_DISTANCE square their field position is in
+    used_indices: HashMap<IVec2, Vec<u32>>,
+    points: Vec<Vec2>,
+    vertexes: Vec<Vec3>,
+    values: Vec<f32>,
+    indices: Vec<u32>,
+}
+
+impl PolygonMeshBuilder{
+    // fans a convex counter clockwise polygon given in field coordinates
+    pub(crate) fn add_polygon(&mut self, polygon: &[(Vec2, f32)], iso_distance: f32){
+        let mut polygon = polygon.to_vec();
+        polygon.dedup_by(|a, b| a.0 == b.0);
+        while polygon.len() > 1 && polygon[0].0 == polygon[polygon.len() - 1].0 {
+            polygon.pop();
+        }
+        if polygon.len() < 3 {
+            return;
+        }
+        let area: f32 = (0..polygon.len())
+            .map(|i| polygon[i].0.perp_dot(polygon[(i + 1) % polygon.len()].0))
+            .sum();
+        if area <= f32::EPSILON {
+            return;
+        }
+        let vertexes: Vec<u32> = polygon
+            .iter()
+            .map(|(point, value)| self.vertex(*point, *value, iso_distance))
+            .collect();
+
+        // a point in the middle of a side would give a triangle without area,
+        // fan those polygons around their center instead
+        let straight = (0..polygon.len()).any(|i| {
+            let [a, b, c] = [0, 1, 2].map(|offset| polygon[(i + offset) % polygon.len()].0);
+            (b - a).perp_dot(c - a).abs() <= f32::EPSILON
+        });
+        if straight {
+            let count = polygon.len() as f32;
+            let center = polygon.iter().map(|(point, _)| *point).sum::<Vec2>() / count;
+            let value = polygon.iter().map(|(_, value)| *value).sum::<f32>() / count;
+            let center = self.vertex(center, value, iso_distance);
+            for i in 0..vertexes.len(){
+                self.triangle([center, vertexes[i], vertexes[(i + 1) % vertexes.len()]]);
+            }
+            return;
+        }
+        for pair in vertexes[1..].windows(2){
+            self.triangle([vertexes[0], pair[0], pair[1]]);
+        }
+    }
+
+    // skips the triangles that welding collapsed
+    fn triangle(&mut self, [a, b, c]: [u32; 3]){
+        if a != b && b != c && c != a {
+            self.indices.extend([a, b, c]);
+        }
+    }
+
+    // the vertex at a point in field coordinates, reusing any vertex within WELD_DISTANCE
+    fn vertex(&mut self, point: Vec2, value: f32, iso_distance: f32) -> u32{
+        let key = (point / WELD_DISTANCE).floor().as_ivec2();
+        for y in -1..=1{
+            for x in -1..=1{
+                let near = self.used_indices.get(&(key + IVec2::new(x, y))).into_iter().flatten();
+                if let Some(indice) = near.copied().find(|indice| self.points[*indice as usize].distance(point) < WELD_DISTANCE){
+                    return indice;
+                }
+            }
+        }
+        let indice = self.vertexes.len() as u32;
+        self.used_indices.entry(key).or_default().push(indice);
+        self.points.push(point);
+        self.vertexes.push((point * iso_distance).extend(0.0));
+        self.values.push(value);
+        indice
+    }
+
+    pub(crate) fn build(self, uvs: &UvGenerator, gradient: Option<&IsoGradient>) -> Mesh{
+        let count = self.vertexes.len();
+        let uvs: Vec<Vec2> = self.vertexes.iter().map(|vertex| uvs.uv(vertex.truncate())).collect();
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.vertexes)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![Vec3::Z; count])
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_indices(Indices::U32(self.indices));
+        if let Some(gradient) = gradient {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vertex_colors(&self.values, gradient));
+        }
+        mesh
+    }
+}
@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::{IsoMeshSettings, IsoSamples};
+
+///How the texture coordinates of the mesh are generated.
+///A `scale` or `width` that isn't positive is clamped to a tiny positive size
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Resource, Default)]
+pub enum IsoUvMode{
+    ///0..1 across the whole field
+    #[default]
+    Field,
+    ///The mesh position divided by `scale`, so the texture repeats every `scale` units
+    World{ scale: f32 },
+    ///For border textures, v is the distance to the contour and u runs along it,
+    ///both divided by `width`. Deeper than `width` inside the shape v stays at 1
+    EdgeDistance{ width: f32 },
+}
+
+// A contour segment in mesh units, with the contour length up to its start
+struct Segment{
+    start: Vec2,
+    end: Vec2,
+    length_before: f32,
+}
+
+// The smallest World scale and EdgeDistance width, smaller ones are clamped to it
+const MIN_UV_SIZE: f32 = 1e-6;
+
+// Works out the uv of a mesh position for the current IsoUvMode
+pub(crate) struct UvGenerator{
+    mode: IsoUvMode,
+    field_size: Vec2,
+    segments: Vec<Segment>,
+    // segments by the `bucket_size` square they touch
+    buckets: HashMap<(i32, i32), Vec<usize>>,
+    // at least the EdgeDistance width, and no smaller than a cell so tiny widths don't
+    // spread a segment over countless buckets
+    bucket_size: f32,
+}
+
+impl UvGenerator{
+    pub(crate) fn new(samples: &IsoSamples, settings: &IsoMeshSettings) -> Self{
+        let field_size = Vec2::new(
+            samples.x_size.saturating_sub(1) as f32,
+            samples.y_size.saturating_sub(1) as f32,
+        ) * settings.iso_distance;
+        // zero, negative or NaN sizes would give infinite uvs
+        let mode = match settings.uv_mode {
+            IsoUvMode::World{ scale } => IsoUvMode::World{ scale: scale.max(MIN_UV_SIZE) },
+            IsoUvMode::EdgeDistance{ width } => IsoUvMode::EdgeDistance{ width: width.max(MIN_UV_SIZE) },
+            mode => mode,
+        };
+        let mut generator = Self{
+            mode,
+            field_size,
+            segments: Vec::new(),
+            buckets: HashMap::new(),
+            bucket_size: 1.0,
+        };
+        if let IsoUvMode::EdgeDistance{ width } = mode {
+            generator.bucket_size = width.max(settings.iso_distance.abs()).max(MIN_UV_SIZE);
+            for line in samples.isolines(settings){
+                let mut length = 0.0;
+                let count = if line.closed { line.points.len() } else { line.points.len().saturating_sub(1) };
+                for i in 0..count{
+                    let start = line.points[i] * settings.iso_distance;
+                    let end = line.points[(i + 1) % line.points.len()] * settings.iso_distance;
+                    generator.add_segment(Segment{ start, end, length_before: length });
+                    length += start.distance(end);
+                }
+            }
+        }
+        generator
+    }
+
+    fn add_segment(&mut self, segment: Segment){
+        let min = (segment.start.min(segment.end) / self.bucket_size).floor();
+        let max = (segment.start.max(segment.end) / self.bucket_size).floor();
+        for y in min.y as i32..=max.y as i32{
+            for x in min.x as i32..=max.x as i32{
+                self.buckets.entry((x, y)).or_default().push(self.segments.len());
+            }
+        }
+        self.segments.push(segment);
+    }
+
+    pub(crate) fn uv(&self, position: Vec2) -> Vec2{
+        match self.mode {
+            // v runs down like the rows of an image
+            IsoUvMode::Field => {
+                let uv = position / self.field_size.max(Vec2::splat(f32::EPSILON));
+                Vec2::new(uv.x, 1.0 - uv.y)
+            }
+            IsoUvMode::World{ scale } => Vec2::new(position.x, -position.y) / scale,
+            IsoUvMode::EdgeDistance{ width } => {
+                // look two buckets out, so vertices just past `width` still get a u that
+                // matches their neighbours closer to the contour
+                let bucket = (position / self.bucket_size).floor();
+                let mut closest = None;
+                for y in -2..=2{
+                    for x in -2..=2{
+                        let key = (bucket.x as i32 + x, bucket.y as i32 + y);
+                        for index in self.buckets.get(&key).into_iter().flatten(){
+                            closest = self.closer(position, *index, closest);
+                        }
+                    }
+                }
+                // deep inside the shape the u of the closest segment anywhere, so it
+                // doesn't jump inside the triangles reaching in from the contour
+                if closest.is_none() {
+                    for index in 0..self.segments.len(){
+                        closest = self.closer(position, index, closest);
+                    }
+                }
+                match closest {
+                    Some((distance, length)) => Vec2::new(length / width, (distance / width).min(1.0)),
+                    None => Vec2::new(0.0, 1.0),
+                }
+            }
+        }
+    }
+
+    // the distance to a segment and the contour length to the closest point on it,
+    // if it is closer than `closest`
+    fn closer(&self, position: Vec2, index: usize, closest: Option<(f32, f32)>) -> Option<(f32, f32)>{
+        let segment = &self.segments[index];
+        let along = segment.end - segment.start;
+        let t = (position - segment.start).dot(along) / along.length_squared().max(f32::EPSILON);
+        let t = t.clamp(0.0, 1.0);
+        let distance = position.distance(segment.start + along * t);
+        if closest.is_none_or(|(closest, _)| distance < closest) {
+            Some((distance, segment.length_before + along.length() * t))
+        } else {
+            closest
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use bevy::render::mesh::VertexAttributeValues;
+    use crate::IsoField;
+
+    // a 9 × 3 field, empty on the left three columns and solid on the rest
+    fn wall() -> IsoField{
+        let mut field = IsoField::new((9, 3));
+        for y in 0..3{
+            for x in 3..9{
+                field.set(x, y, 2.0);
+            }
+        }
+        field
+    }
+
+    #[test]
+    fn field_uvs_span_the_field(){
+        let settings = IsoMeshSettings{ iso_distance: 0.5, ..default() };
+        let mesh = wall().sample_all().build_mesh(&settings);
+        let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else { panic!("no uvs") };
+        let (min, max) = uvs.iter().fold((Vec2::MAX, Vec2::MIN), |(min, max), uv| (min.min(Vec2::from(*uv)), max.max(Vec2::from(*uv))));
+        // the mesh starts at the contour, half way between columns 2 and 3
+        assert!(min.abs_diff_eq(Vec2::new(2.5 / 8.0, 0.0), 1e-5), "{min}");
+        assert!(max.abs_diff_eq(Vec2::ONE, 1e-5), "{max}");
+
+        let generator = UvGenerator::new(&wall().sample_all(), &settings);
+        assert_eq!(generator.uv(Vec2::ZERO), Vec2::new(0.0, 1.0));
+        assert_eq!(generator.uv(Vec2::new(4.0, 1.0)), Vec2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn world_uvs_scale(){
+        let samples = wall().sample_all();
+        for scale in [1.0, 2.0, 4.0]{
+            let settings = IsoMeshSettings{ uv_mode: IsoUvMode::World{ scale }, ..default() };
+            let generator = UvGenerator::new(&samples, &settings);
+            assert_eq!(generator.uv(Vec2::new(4.0, 2.0)), Vec2::new(4.0, -2.0) / scale);
+        }
+        let settings = IsoMeshSettings{ uv_mode: IsoUvMode::World{ scale: 0.0 }, ..default() };
+        assert!(UvGenerator::new(&samples, &settings).uv(Vec2::ONE).is_finite());
+    }
+
+    #[test]
+    fn edge_distance(){
+        let settings = IsoMeshSettings{ uv_mode: IsoUvMode::EdgeDistance{ width: 2.0 }, ..default() };
+        let generator = UvGenerator::new(&wall().sample_all(), &settings);
+        let on_contour = generator.uv(Vec2::new(2.5, 1.0));
+        assert!(on_contour.y.abs() < 1e-5, "{on_contour}");
+        let halfway = generator.uv(Vec2::new(3.5, 1.0));
+        assert!((halfway.y - 0.5).abs() < 1e-5, "{halfway}");
+        let at_width = generator.uv(Vec2::new(4.5, 1.0));
+        assert!((at_width.y - 1.0).abs() < 1e-5, "{at_width}");
+        // more than two buckets from the contour u still comes from the closest point on it
+        let deep = generator.uv(Vec2::new(8.0, 1.0));
+        assert_eq!(deep.y, 1.0);
+        assert!((deep.x - on_contour.x).abs() < 1e-5, "{deep} {on_contour}");
+        // u runs along the contour
+        let along = generator.uv(Vec2::new(2.5, 2.0));
+        assert!(((along.x - on_contour.x).abs() - 0.5).abs() < 1e-5, "{along} {on_contour}");
+    }
+}
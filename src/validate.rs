@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+use bevy::{
+    render::{
+        mesh::{Indices, VertexAttributeValues}, render_resource::PrimitiveTopology
+    }, utils::HashMap,
+};
+
+///What validate_mesh found wrong with a mesh, every list is empty for a clean mesh.
+///Vertices are welded by position first, so the indices in the edges are the
+///first vertex at each position
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IsoMeshReport{
+    ///Triangles without area, by triangle index. Tiny is measured against the
+    ///average edge of the mesh, so it works at any IsoDistance
+    pub degenerate: Vec<usize>,
+    ///Triangles wound clockwise when seen from the side their vertex normals face
+    pub flipped: Vec<usize>,
+    ///Edges used twice in the same direction, the triangles on them disagree on winding
+    pub inconsistent_edges: Vec<[u32; 2]>,
+    ///Edges shared by more than two triangles
+    pub non_manifold_edges: Vec<[u32; 2]>,
+    ///Open edges with another vertex lying on them, as (vertex, edge)
+    pub t_junctions: Vec<(u32, [u32; 2])>,
+}
+
+impl IsoMeshReport{
+    pub fn is_valid(&self) -> bool{
+        self.degenerate.is_empty()
+            && self.flipped.is_empty()
+            && self.inconsistent_edges.is_empty()
+            && self.non_manifold_edges.is_empty()
+            && self.t_junctions.is_empty()
+    }
+}
+
+///Checks a triangle list mesh, like the ones build_mesh and the other builders make.
+///Meshes with another topology or without positions give an empty report
+pub fn validate_mesh(mesh: &Mesh) -> IsoMeshReport{
+    let mut report = IsoMeshReport::default();
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return report;
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return report;
+    };
+    let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+        Some(VertexAttributeValues::Float32x3(normals)) => Some(normals),
+        _ => None,
+    };
+    let indices: Vec<u32> = match mesh.indices() {
+        Some(Indices::U32(indices)) => indices.clone(),
+        Some(Indices::U16(indices)) => indices.iter().map(|index| *index as u32).collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+
+    // the first vertex at each position, walls and caps don't share vertices
+    let mut first_at = HashMap::<[u32; 3], u32>::new();
+    let welded: Vec<u32> = positions
+        .iter()
+        .enumerate()
+        .map(|(index, position)| *first_at.entry(position.map(f32::to_bits)).or_insert(index as u32))
+        .collect();
+    let position = |index: u32| Vec3::from(positions[index as usize]);
+
+    // the area a triangle needs scales with the cells, so the tolerance is relative to the average edge
+    let edge_count = indices.len() / 3 * 3;
+    let average_edge = indices
+        .chunks_exact(3)
+        .flat_map(|tri| [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])])
+        .map(|(from, to)| position(from).distance(position(to)))
+        .sum::<f32>()
+        / edge_count.max(1) as f32;
+    let min_area = average_edge * average_edge * 1e-7;
+
+    let mut edges = HashMap::<[u32; 2], usize>::new();
+    for (triangle, tri) in indices.chunks_exact(3).enumerate(){
+        let [a, b, c] = [tri[0], tri[1], tri[2]];
+        let face = (position(b) - position(a)).cross(position(c) - position(a));
+        if face.length() <= min_area {
+            report.degenerate.push(triangle);
+            continue;
+        }
+        if let Some(normals) = normals {
+            let normal: Vec3 = [a, b, c].iter().map(|index| Vec3::from(normals[*index as usize])).sum();
+            if face.dot(normal) < 0.0 {
+                report.flipped.push(triangle);
+            }
+        }
+        for (from, to) in [(a, b), (b, c), (c, a)]{
+            *edges.entry([welded[from as usize], welded[to as usize]]).or_default() += 1;
+        }
+    }
+
+    let mut open_edges = Vec::new();
+    for (&[from, to], &count) in edges.iter(){
+        let reverse = edges.get(&[to, from]).copied().unwrap_or(0);
+        if count > 1 {
+            report.inconsistent_edges.push([from, to]);
+        }
+        // counted from the lower vertex so every edge is only reported once
+        if from < to && count + reverse > 2 {
+            report.non_manifold_edges.push([from, to]);
+        }
+        if reverse == 0 {
+            open_edges.push([from, to]);
+        }
+    }
+
+    // a vertex in the middle of another triangle's edge is the end of two open edges itself,
+    // so only the vertices of open edges need checking
+    let open_vertices: Vec<u32> = {
+        let mut vertices: Vec<u32> = open_edges.iter().flatten().copied().collect();
+        vertices.sort_unstable();
+        vertices.dedup();
+        vertices
+    };
+    if !open_edges.is_empty() {
+        let cell = open_edges
+            .iter()
+            .map(|[from, to]| position(*from).distance(position(*to)))
+            .sum::<f32>()
+            / open_edges.len() as f32;
+        let cell = cell.max(f32::EPSILON);
+        let key = |point: Vec3| (point / cell).floor().as_ivec3();
+        let mut grid = HashMap::<IVec3, Vec<u32>>::new();
+        for vertex in open_vertices{
+            grid.entry(key(position(vertex))).or_default().push(vertex);
+        }
+        for [from, to] in open_edges{
+            let (start, end) = (position(from), position(to));
+            let along = end - start;
+            let (min, max) = (key(start.min(end)), key(start.max(end)));
+            for x in min.x..=max.x{
+                for y in min.y..=max.y{
+                    for z in min.z..=max.z{
+                        for vertex in grid.get(&IVec3::new(x, y, z)).into_iter().flatten(){
+                            if *vertex == from || *vertex == to {
+                                continue;
+                            }
+                            let t = (position(*vertex) - start).dot(along) / along.length_squared();
+                            let distance = position(*vertex).distance(start + along * t);
+                            if t > 0.0 && t < 1.0 && distance <= cell * 1e-4 {
+                                report.t_junctions.push((*vertex, [from, to]));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    report.inconsistent_edges.sort_unstable();
+    report.non_manifold_edges.sort_unstable();
+    report.t_junctions.sort_unstable();
+    report
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::noise::hash;
+    use crate::{IsoField, IsoMeshSettings, SaddleResolution};
+
+    fn assert_valid(field: &IsoField, name: &str){
+        for saddle in [SaddleResolution::Join, SaddleResolution::Split, SaddleResolution::Decide]{
+            for greedy_merge in [false, true]{
+                for (iso_distance, invert) in [(1.0, false), (0.01, false), (1.0, true)]{
+                    let settings = IsoMeshSettings{ iso_level: 0.5, iso_distance, invert, saddle, greedy_merge, ..default() };
+                    let report = validate_mesh(&field.sample_all().build_mesh(&settings));
+                    assert!(report.is_valid(), "{name} {saddle:?} merge {greedy_merge} distance {iso_distance} invert {invert}: {report:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_case_is_valid(){
+        for case in 0..16{
+            let values = (0..4).map(|corner| if case & (1 << corner) != 0 { 1.0 } else { 0.0 }).collect();
+            assert_valid(&IsoField::new_from((2, 2), values), &format!("case {case}"));
+        }
+    }
+
+    #[test]
+    fn random_fields_are_valid(){
+        // samples right on the level as well, and solid areas big enough to merge
+        for seed in 0..200{
+            let values = (0..64).map(|i| [0.0, 0.5, 1.0, 1.0][hash(i, 0, seed) as usize % 4]).collect();
+            assert_valid(&IsoField::new_from((8, 8), values), &format!("seed {seed}"));
+        }
+    }
+}